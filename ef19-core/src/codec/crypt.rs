@@ -0,0 +1,108 @@
+use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+use sha1::{Digest, Sha1};
+use crate::errors::EResult;
+
+const GJP_KEY: &[u8] = b"37526";
+const GJP2_SALT: &str = "mI29fmAnxgTs";
+const LEVEL_PASSWORD_KEY: &[u8] = b"26364";
+const LEVEL_CHK_KEY: &[u8] = b"41274";
+const LEVEL_CHK_SALT: &str = "xI25fpAapCQg";
+// how many characters of the level string go into an upload's seed2
+const LEVEL_SEED_SAMPLES: usize = 50;
+
+// xor every byte against the key, starting over at the end of the key
+pub fn xor_cycle(data: &[u8], key: &[u8]) -> Vec<u8> {
+    data.iter().zip(key.iter().cycle()).map(|(d, k)| d ^ k).collect()
+}
+
+fn sha1_hex(data: &str) -> String {
+    Sha1::digest(data.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+// xor then base64, the usual way things get hidden in requests
+fn encode_xor(data: &str, key: &[u8]) -> String {
+    URL_SAFE.encode(xor_cycle(data.as_bytes(), key))
+}
+
+fn decode_xor(data: &str, key: &[u8]) -> EResult<String> {
+    let bytes = xor_cycle(&URL_SAFE.decode(data)?, key);
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// account password as sent in the gjp field, what 1.9 and most private servers use
+pub fn encode_gjp(password: &str) -> String {
+    encode_xor(password, GJP_KEY)
+}
+
+pub fn decode_gjp(gjp: &str) -> EResult<String> {
+    decode_xor(gjp, GJP_KEY)
+}
+
+// 2.2's gjp2, only a hash so there's no going back
+pub fn encode_gjp2(password: &str) -> String {
+    sha1_hex(&format!("{password}{GJP2_SALT}"))
+}
+
+// level copy passwords as servers send them (key 27)
+pub fn encode_level_password(password: &str) -> String {
+    encode_xor(password, LEVEL_PASSWORD_KEY)
+}
+
+pub fn decode_level_password(encoded: &str) -> EResult<String> {
+    decode_xor(encoded, LEVEL_PASSWORD_KEY)
+}
+
+// checksum the game sends with some requests, every endpoint has its own salt and key
+pub fn chk(values: &[&str], salt: &str, key: &[u8]) -> String {
+    let hash = sha1_hex(&(values.concat() + salt));
+    encode_xor(&hash, key)
+}
+
+// seed2 for uploads, a checksum over evenly spaced characters of the level string
+pub fn level_seed2(level_string: &str) -> String {
+    let bytes = level_string.as_bytes();
+    let sample: String = if bytes.len() < LEVEL_SEED_SAMPLES {
+        level_string.to_string()
+    } else {
+        let step = bytes.len() / LEVEL_SEED_SAMPLES;
+        bytes.iter().step_by(step).take(LEVEL_SEED_SAMPLES).map(|&b| b as char).collect()
+    };
+    chk(&[&sample], LEVEL_CHK_SALT, LEVEL_CHK_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn xor() {
+        let data = b"1.9 forever";
+        let xored = xor_cycle(data, b"key");
+        assert_eq!(&xored[..3], &[b'1' ^ b'k', b'.' ^ b'e', b'9' ^ b'y']);
+        assert_eq!(xored[3], b' ' ^ b'k');
+        assert_eq!(xor_cycle(&xored, b"key"), data);
+        assert!(xor_cycle(b"", b"key").is_empty());
+    }
+    
+    #[test]
+    fn passwords() {
+        assert_eq!(encode_gjp("hunter2"), "W0JbRlNBBQ==");
+        assert_eq!(decode_gjp("W0JbRlNBBQ==").unwrap(), "hunter2");
+        assert!(decode_gjp("not base64!").is_err());
+        assert_eq!(encode_gjp2("hunter2"), "dcc58eb53f0c1604c19240834e7fd12e73f9a9c7");
+        
+        // free copy, and a 123 copy password with the leading 1 and padding
+        assert_eq!(encode_level_password("1"), "Aw==");
+        assert_eq!(encode_level_password("1000123"), "AwYDBgUABQ==");
+        assert_eq!(decode_level_password("AwYDBgUABQ==").unwrap(), "1000123");
+    }
+    
+    #[test]
+    fn checksums() {
+        assert_eq!(chk(&["a", "bc"], LEVEL_CHK_SALT, LEVEL_CHK_KEY), "AgABAgcCBgRVDQZQBQNWVQYBBw0CUFYODAVXBlFWDQMBVlBQV1RUAg==");
+        let level = "H4sIAAAAAAAAC6WQwQ3CMAxFF_IhdpJWVU_MwAB_gK7ACu3eMhA3F-dl2-uLMfMJHDQ7EZ6wGbODaNPt9aA";
+        assert_eq!(level_seed2(level), "AVdQAVFXCAAAAgIBAgZSV1cLBwBXAlQABg0GUwUNA1BUBAVVUFcDVQ==");
+        // short strings are used whole
+        assert_eq!(level_seed2("abc"), chk(&["abc"], LEVEL_CHK_SALT, LEVEL_CHK_KEY));
+    }
+}
@@ -56,15 +56,15 @@ impl From<XmlEvent<'_>> for FormatError {
             XmlEvent::End(e) => Self::End(escaped_string(e.name().0)),
             XmlEvent::Empty(e) => Self::Empty(escaped_string(e.name().0)),
             XmlEvent::Text(e) => {
-                if (&*e).len() < 20 { Self::Text(escaped_string_quotes(&*e)) }
+                if e.len() < 20 { Self::Text(escaped_string_quotes(&e)) }
                 else {
                     let mut string = escaped_string_quotes(&e[..17]);
                     string.push_str("...");
                     Self::Text(string)
                 }
             },
-            XmlEvent::CData(e) => Self::CData,
-            XmlEvent::PI(e) => Self::PI,
+            XmlEvent::CData(_) => Self::CData,
+            XmlEvent::PI(_) => Self::PI,
             XmlEvent::Eof => Self::Eof,
             _ => { panic!("{event:?} not implemented for unexpected event error"); }
         }
@@ -128,7 +128,7 @@ pub type GmdResult<T> = std::result::Result<T, GmdError>;
 // public function for reading GmdValues into .gmd files
 pub fn gmd_to_bytes(value: GmdValue) -> Option<Vec<u8>> {
     let mut cursor = Cursor::new(Vec::new());
-    cursor.write(br#"<?xml version="1.0"?>"#).ok()?;
+    cursor.write_all(br#"<?xml version="1.0"?>"#).ok()?;
     let mut writer = XmlWriter::new(cursor);
     
     writer.create_element("plist")
@@ -177,7 +177,7 @@ fn write_dict<W: Write>(writer: &mut XmlWriter<W>, dict: &HashMap<String, GmdVal
 
 // public function for reading .gmd files into GmdValues
 pub fn gmd_from_bytes(bytes: &[u8]) -> GmdResult<GmdValue> {
-    let mut reader = XmlReader::from_reader(bytes);
+    let reader = XmlReader::from_reader(bytes);
     
     gmd_from_xml_reader(reader)
 }
@@ -274,6 +274,7 @@ fn next_gmd_event<'a>(reader: &mut XmlReader<&'a [u8]>) -> GmdResult<XmlEvent<'a
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::io::Error as IoError;
+use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+use crate::codec::gdshare::{gmd_from_bytes, gmd_to_bytes, GmdValue};
+use crate::models::level::{Level, Password, Song};
+use crate::errors::{Error, EResult, KeyError};
+
+// saved levels are a dict of these, the same ones the game uses in CCLocalLevels
+const NAME_KEY: &str = "k2";
+const DESCRIPTION_KEY: &str = "k3";
+const OBJECTS_KEY: &str = "k4";
+const OFFICIAL_SONG_KEY: &str = "k8";
+const VERSION_KEY: &str = "k16";
+const LENGTH_KEY: &str = "k23";
+const CUSTOM_SONG_KEY: &str = "k45";
+const PASSWORD_KEY: &str = "k41";
+const BINARY_VERSION_KEY: &str = "k50";
+// 4 marks the dict as a level
+const TYPE_KEY: &str = "kCEK";
+
+fn int(dict: &HashMap<String, GmdValue>, key: &str) -> u32 {
+    match dict.get(key) {
+        Some(GmdValue::Int(v)) => *v as u32,
+        Some(GmdValue::Str(v)) => v.parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn string(dict: &HashMap<String, GmdValue>, key: &str) -> Option<String> {
+    match dict.get(key) {
+        Some(GmdValue::Str(v)) => Some(v.clone()),
+        _ => None,
+    }
+}
+
+impl Level {
+    pub fn from_gmd(bytes: &[u8]) -> EResult<Level> {
+        let GmdValue::Dict(dict) = gmd_from_bytes(bytes)? else {
+            return Err(KeyError::Missing { key: OBJECTS_KEY.to_string() }.into());
+        };
+        let object_str = string(&dict, OBJECTS_KEY)
+            .ok_or_else(|| KeyError::Missing { key: OBJECTS_KEY.to_string() })?;
+        // descriptions are base64, older files sometimes have it in plain text
+        let description = string(&dict, DESCRIPTION_KEY).map(|d| {
+            URL_SAFE.decode(&d).ok().and_then(|d| String::from_utf8(d).ok()).unwrap_or(d)
+        });
+        let song = match int(&dict, CUSTOM_SONG_KEY) {
+            0 => Song::Official(int(&dict, OFFICIAL_SONG_KEY)),
+            id => Song::Custom(id),
+        };
+        
+        Ok(Level {
+            name: string(&dict, NAME_KEY).unwrap_or_default(),
+            description: description.unwrap_or_default(),
+            object_str,
+            object_list: None,
+            song,
+            password: Password::from_plain(&int(&dict, PASSWORD_KEY).to_string()).unwrap_or_default(),
+            version: int(&dict, VERSION_KEY).max(1),
+            // gmd files don't say which game version saved them
+            game_version: 0,
+            binary_version: int(&dict, BINARY_VERSION_KEY),
+            length: int(&dict, LENGTH_KEY),
+            is_two_player: false,
+            object_count: 0,
+            has_low_detail: false,
+        })
+    }
+    
+    pub fn to_gmd(&self) -> EResult<Vec<u8>> {
+        let mut dict = HashMap::new();
+        dict.insert(TYPE_KEY.to_string(), GmdValue::Int(4));
+        dict.insert(NAME_KEY.to_string(), GmdValue::Str(self.name.clone()));
+        if !self.description.is_empty() {
+            dict.insert(DESCRIPTION_KEY.to_string(), GmdValue::Str(URL_SAFE.encode(&self.description)));
+        }
+        dict.insert(OBJECTS_KEY.to_string(), GmdValue::Str(self.object_string()?));
+        match self.song {
+            Song::Official(id) => dict.insert(OFFICIAL_SONG_KEY.to_string(), GmdValue::Int(id as i32)),
+            Song::Custom(id) => dict.insert(CUSTOM_SONG_KEY.to_string(), GmdValue::Int(id as i32)),
+        };
+        dict.insert(VERSION_KEY.to_string(), GmdValue::Int(self.version as i32));
+        if self.password != Password::NoCopy {
+            let password = self.password.to_plain().parse().unwrap_or(0);
+            dict.insert(PASSWORD_KEY.to_string(), GmdValue::Int(password));
+        }
+        dict.insert(LENGTH_KEY.to_string(), GmdValue::Int(self.length as i32));
+        if self.binary_version != 0 {
+            dict.insert(BINARY_VERSION_KEY.to_string(), GmdValue::Int(self.binary_version as i32));
+        }
+        
+        gmd_to_bytes(GmdValue::Dict(dict))
+            .ok_or_else(|| Error::Io(IoError::other("couldn't write gmd")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec;
+    
+    #[test]
+    fn gmd_round_trip() {
+        let objects = codec::zip_string("kA2,0;1,1,2,15,3,15;").unwrap();
+        let gmd = format!(
+            "<?xml version=\"1.0\"?><plist version=\"1.0\" gjver=\"2.0\"><dict><k>kCEK</k><i>4</i><k>k2</k><s>awawa</s>\
+            <k>k3</k><s>{}</s><k>k4</k><s>{}</s><k>k41</k><i>1000123</i><k>k45</k><i>467339</i><k>k50</k><i>35</i></dict></plist>",
+            URL_SAFE.encode("hi"),
+            objects,
+        );
+        let mut level = Level::from_gmd(gmd.as_bytes()).unwrap();
+        assert_eq!(level.name(), "awawa");
+        assert_eq!(level.description, "hi");
+        assert_eq!(level.song, Song::Custom(467339));
+        assert_eq!(level.password(), Password::Code(123));
+        assert_eq!(level.object_list().unwrap().len(), 1);
+        
+        let again = Level::from_gmd(&level.to_gmd().unwrap()).unwrap();
+        assert_eq!(again.name(), "awawa");
+        assert_eq!(again.description, "hi");
+        assert_eq!(again.binary_version, 35);
+        assert_eq!(again.password(), Password::Code(123));
+        assert_eq!(codec::unzip_string(&again.object_str).unwrap(), "kA2,0;1,1,2,15,3,15,4,0,5,0,6,0;");
+    }
+}
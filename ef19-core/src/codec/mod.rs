@@ -1,6 +1,5 @@
 use std::{
     collections::HashMap,
-    error::Error,
     io::Read,
     io::Error as IoError,
     fmt,
@@ -15,6 +14,8 @@ use flate2::{
 pub mod gdshare;
 pub mod server;
 pub mod format;
+pub mod gmdfile;
+pub mod crypt;
 
 pub fn escaped_string(raw: &[u8]) -> String {
     let mut string = String::new();
@@ -60,9 +61,11 @@ pub fn deserialise_kv(input: &str, sep: &str) -> HashMap<String, String> {
 }
 
 // serialise k:v:k:v style string
+// keys are sorted (numeric ones first, by value) so the same map always gives the same string
 pub fn serialise_kv(map: &HashMap<String, String>, sep: &str) -> String {
     let mut serialised = String::new();
-        let mut kvs = map.iter();
+        let mut kvs = map.iter()
+            .sorted_by_cached_key(|(k, _)| (k.parse::<u32>().unwrap_or(u32::MAX), k.as_str()));
         if let Some((k, v)) = kvs.next() {
             serialised.push_str(k);
             serialised.push_str(sep);
@@ -137,7 +140,7 @@ pub fn unzip_string(zipped: &str) -> Result<String, ZipError> {
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    
     #[test]
     fn serialise() {
         let mut map: HashMap<String, String> = HashMap::new();
@@ -160,6 +163,16 @@ mod tests {
         }
     }
     #[test]
+    fn serialise_order() {
+        let mut map: HashMap<String, String> = HashMap::new();
+        map.insert("kA2".to_string(), "0".to_string());
+        map.insert("10".to_string(), "a".to_string());
+        map.insert("2".to_string(), "b".to_string());
+        map.insert("1".to_string(), "c".to_string());
+        
+        assert_eq!(serialise_kv(&map, ","), "1,c,2,b,10,a,kA2,0");
+    }
+    #[test]
     fn deserialise() {
         let object = "1:2:3:4:5:6:8:shit";
         let map = deserialise_kv(object, ":");
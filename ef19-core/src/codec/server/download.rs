@@ -0,0 +1,87 @@
+use crate::codec::deserialise_kv;
+use crate::codec::server::{post, GdTransport, ServerError, ServerProfile};
+use crate::codec::server::level::{
+    GAME_VERSION_KEY, LENGTH_KEY, OBJECT_COUNT_KEY, OFFICIAL_SONG_KEY, CUSTOM_SONG_KEY,
+};
+use crate::codec::server::search::find_level_map;
+use crate::models::level::Level;
+use crate::errors::EResult;
+
+// older servers and levels leave these out of downloads, searches always have them
+const SEARCH_ONLY_KEYS: [&str; 5] = [GAME_VERSION_KEY, LENGTH_KEY, OBJECT_COUNT_KEY, OFFICIAL_SONG_KEY, CUSTOM_SONG_KEY];
+
+pub fn download_level<T: GdTransport>(transport: &T, profile: &ServerProfile, id: u32) -> EResult<Level> {
+    let mut form = profile.base_form();
+    form.push(("levelID", id.to_string()));
+    let response = post(transport, &profile.endpoints.download, &form)?
+        .ok_or(ServerError::NotFound)?;
+    
+    // level#hash#hash, some servers add a creator segment after the hashes
+    let level = response.split('#').next().unwrap_or_default();
+    let mut map = deserialise_kv(level, ":");
+    if SEARCH_ONLY_KEYS.iter().any(|key| !map.contains_key(*key)) {
+        // missing metadata isn't worth failing the download over
+        if let Ok(Some(found)) = find_level_map(transport, profile, id) {
+            for key in SEARCH_ONLY_KEYS {
+                if let Some(value) = found.get(key) {
+                    map.entry(key.to_string()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+    }
+    Level::from_server_map(&map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec;
+    use crate::codec::server::profile::{DOWNLOAD_ENDPOINT, SEARCH_ENDPOINT};
+    use crate::codec::server::MemoryTransport;
+    use crate::errors::{Error, KeyError};
+    use crate::models::level::{Password, Song};
+    
+    fn reply(extra: &str) -> String {
+        let objects = codec::zip_string("kA2,0;1,1,2,15,3,15;").unwrap();
+        format!("1:128:2:1st level:3:bXkgbGV2ZWw=:4:{objects}:5:3{extra}#a94a8fe5ccb19ba61c4c0873d391e987982fbbd3#6b2d6fb1")
+    }
+    
+    #[test]
+    fn download() {
+        let transport = MemoryTransport::new()
+            .respond(DOWNLOAD_ENDPOINT, &reply(":13:21:15:2:12:4:35:0:45:1:27:Aw=="));
+        let mut level = download_level(&transport, &ServerProfile::official(), 128).unwrap();
+        assert_eq!(level.name(), "1st level");
+        assert_eq!(level.description, "my level");
+        assert_eq!((level.version, level.game_version, level.length, level.object_count), (3, 21, 2, 1));
+        assert_eq!(level.song, Song::Official(4));
+        assert_eq!(level.password(), Password::FreeCopy);
+        assert_eq!(level.object_list().unwrap().len(), 1);
+        
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].field("levelID"), Some("128"));
+        assert_eq!(requests[0].field("secret"), Some("Wmfd2893gb7"));
+        assert_eq!(requests[0].field("gameVersion"), Some("22"));
+        
+        // a password that won't decode fails the download instead of quietly losing the copy code
+        let transport = MemoryTransport::new().respond(DOWNLOAD_ENDPOINT, &reply(":27:YQ=="));
+        let result = download_level(&transport, &ServerProfile::official(), 128);
+        assert!(matches!(result, Err(Error::Key(KeyError::Invalid { .. }))));
+    }
+    
+    #[test]
+    fn search_fallback() {
+        let transport = MemoryTransport::new()
+            .respond(DOWNLOAD_ENDPOINT, &reply(""))
+            .respond(SEARCH_ENDPOINT, "1:127:2:other|1:128:2:1st level:13:20:15:1:12:0:35:546561:45:1#4:RobTop:71#1~|~546561#1:0:10#hash");
+        let level = download_level(&transport, &ServerProfile::official(), 128).unwrap();
+        assert_eq!((level.game_version, level.length), (20, 1));
+        assert_eq!(level.song, Song::Custom(546561));
+        assert_eq!(transport.requests()[1].field("str"), Some("128"));
+        
+        let transport = MemoryTransport::new().respond(DOWNLOAD_ENDPOINT, "-1");
+        let result = download_level(&transport, &ServerProfile::official(), 1);
+        assert!(matches!(result, Err(Error::Server(ServerError::NotFound))));
+    }
+}
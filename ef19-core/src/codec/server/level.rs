@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+use crate::codec::deserialise_kv;
+use crate::models::level::{Level, Password, Song};
+use crate::errors::{EResult, KeyError};
+
+// keys in the 1:x:2:x strings servers send levels as
+pub(crate) const ID_KEY: &str = "1";
+const NAME_KEY: &str = "2";
+const DESCRIPTION_KEY: &str = "3";
+pub(crate) const OBJECTS_KEY: &str = "4";
+const VERSION_KEY: &str = "5";
+const USER_ID_KEY: &str = "6";
+const DOWNLOADS_KEY: &str = "10";
+pub(crate) const OFFICIAL_SONG_KEY: &str = "12";
+pub(crate) const GAME_VERSION_KEY: &str = "13";
+const LIKES_KEY: &str = "14";
+pub(crate) const LENGTH_KEY: &str = "15";
+const PASSWORD_KEY: &str = "27";
+const TWO_PLAYER_KEY: &str = "31";
+pub(crate) const CUSTOM_SONG_KEY: &str = "35";
+const LOW_DETAIL_KEY: &str = "40";
+pub(crate) const OBJECT_COUNT_KEY: &str = "45";
+
+fn int(map: &HashMap<String, String>, key: &str) -> u32 {
+    map.get(key).and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+fn flag(map: &HashMap<String, String>, key: &str) -> bool {
+    map.get(key).is_some_and(|v| v == "1")
+}
+
+// descriptions are base64 from 2.0 on
+fn description(map: &HashMap<String, String>) -> String {
+    map.get(DESCRIPTION_KEY).map(|d| {
+        URL_SAFE.decode(d).ok().and_then(|d| String::from_utf8(d).ok()).unwrap_or(d.clone())
+    }).unwrap_or_default()
+}
+
+fn song(map: &HashMap<String, String>) -> Song {
+    match int(map, CUSTOM_SONG_KEY) {
+        0 => Song::Official(int(map, OFFICIAL_SONG_KEY)),
+        id => Song::Custom(id),
+    }
+}
+
+// what a search says about a level, everything but the objects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelInfo {
+    pub id: u32,
+    pub name: String,
+    pub description: String,
+    // the creator's user id, not their account id
+    pub user_id: u32,
+    pub version: u32,
+    pub game_version: u32,
+    pub length: u32,
+    pub song: Song,
+    pub object_count: u32,
+    pub is_two_player: bool,
+    pub downloads: u32,
+    // can go negative on servers that still have dislikes
+    pub likes: i32,
+}
+
+impl LevelInfo {
+    pub fn from_server_map(map: &HashMap<String, String>) -> EResult<LevelInfo> {
+        let id = map.get(ID_KEY)
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| KeyError::Missing { key: ID_KEY.to_string() })?;
+        Ok(LevelInfo {
+            id,
+            name: map.get(NAME_KEY).cloned().unwrap_or_default(),
+            description: description(map),
+            user_id: int(map, USER_ID_KEY),
+            version: int(map, VERSION_KEY).max(1),
+            game_version: int(map, GAME_VERSION_KEY),
+            length: int(map, LENGTH_KEY),
+            song: song(map),
+            object_count: int(map, OBJECT_COUNT_KEY),
+            is_two_player: flag(map, TWO_PLAYER_KEY),
+            downloads: int(map, DOWNLOADS_KEY),
+            likes: map.get(LIKES_KEY).and_then(|v| v.parse().ok()).unwrap_or(0),
+        })
+    }
+}
+
+impl Level {
+    // the level part of a download reply, without any of the # segments after it
+    pub fn from_server_string(string: &str) -> EResult<Level> {
+        Level::from_server_map(&deserialise_kv(string, ":"))
+    }
+    
+    pub fn from_server_map(map: &HashMap<String, String>) -> EResult<Level> {
+        let object_str = map.get(OBJECTS_KEY)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| KeyError::Missing { key: OBJECTS_KEY.to_string() })?;
+        let password = match map.get(PASSWORD_KEY) {
+            Some(password) => Password::from_server(password)?,
+            None => Password::NoCopy,
+        };
+        
+        Ok(Level {
+            name: map.get(NAME_KEY).cloned().unwrap_or_default(),
+            description: description(map),
+            object_str: object_str.clone(),
+            object_list: None,
+            song: song(map),
+            password,
+            version: int(map, VERSION_KEY).max(1),
+            game_version: int(map, GAME_VERSION_KEY),
+            binary_version: 0,
+            length: int(map, LENGTH_KEY),
+            is_two_player: flag(map, TWO_PLAYER_KEY),
+            object_count: int(map, OBJECT_COUNT_KEY),
+            has_low_detail: flag(map, LOW_DETAIL_KEY),
+        })
+    }
+}
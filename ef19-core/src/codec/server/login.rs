@@ -0,0 +1,192 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use crate::codec::crypt::{encode_gjp, encode_gjp2};
+use crate::codec::server::{post, GdTransport, ServerError, ServerProfile};
+use crate::errors::{EResult, KeyError};
+
+// where credentials come from, they're never taken as arguments so they stay out of shell history
+pub const USER_NAME_VAR: &str = "EF19_USERNAME";
+pub const PASSWORD_VAR: &str = "EF19_PASSWORD";
+pub const UDID_VAR: &str = "EF19_UDID";
+
+#[derive(Clone, Deserialize)]
+pub struct Credentials {
+    #[serde(alias = "username")]
+    pub user_name: String,
+    pub password: String,
+    // device id the account gets tied to, made up from the user name if not given
+    #[serde(default)]
+    pub udid: Option<String>,
+}
+
+// keeps the password out of logs
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("user_name", &self.user_name)
+            .field("udid", &self.udid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    pub fn new(user_name: &str, password: &str) -> Credentials {
+        Credentials { user_name: user_name.to_string(), password: password.to_string(), udid: None }
+    }
+    
+    pub fn from_env() -> EResult<Credentials> {
+        let var = |key: &str| env::var(key).map_err(|_| KeyError::Missing { key: key.to_string() });
+        Ok(Credentials {
+            user_name: var(USER_NAME_VAR)?,
+            password: var(PASSWORD_VAR)?,
+            udid: env::var(UDID_VAR).ok(),
+        })
+    }
+    
+    // json with user_name (or username), password and optionally udid
+    pub fn from_file(path: impl AsRef<Path>) -> EResult<Credentials> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+    
+    pub fn udid(&self) -> String {
+        match &self.udid {
+            Some(udid) => udid.clone(),
+            None => {
+                let digits: String = encode_gjp2(&self.user_name).bytes().take(10).map(|b| char::from(b'0' + b % 10)).collect();
+                format!("S{digits}")
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginError {
+    WrongCredentials,
+    Banned,
+    // the account was never activated by email
+    Unactivated,
+}
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongCredentials => write!(f, "wrong user name or password"),
+            Self::Banned => write!(f, "account is banned"),
+            Self::Unactivated => write!(f, "account isn't activated"),
+        }
+    }
+}
+impl std::error::Error for LoginError {}
+
+impl LoginError {
+    pub fn from_code(code: i32) -> Option<LoginError> {
+        match code {
+            -1 | -11 => Some(LoginError::WrongCredentials),
+            -12 => Some(LoginError::Banned),
+            -13 => Some(LoginError::Unactivated),
+            _ => None,
+        }
+    }
+}
+
+// a logged in account, everything account bound requests need
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub user_name: String,
+    pub account_id: u32,
+    pub user_id: u32,
+    pub udid: String,
+    pub(crate) gjp: String,
+}
+
+impl Session {
+    pub fn gjp(&self) -> &str {
+        &self.gjp
+    }
+}
+
+// "accountID,userID" or a negative code
+fn parse_login_reply(reply: &str) -> Result<(u32, u32), ServerError> {
+    if let Some(code) = reply.parse::<i32>().ok().filter(|&code| code < 0) {
+        return Err(match LoginError::from_code(code) {
+            Some(e) => ServerError::Login(e),
+            None => ServerError::Rejected(code),
+        });
+    }
+    reply.split_once(',')
+        .and_then(|(account, user)| Some((account.parse().ok()?, user.parse().ok()?)))
+        .ok_or_else(|| ServerError::Malformed(reply.to_string()))
+}
+
+pub fn login<T: GdTransport>(transport: &T, profile: &ServerProfile, credentials: &Credentials) -> EResult<Session> {
+    let udid = credentials.udid();
+    let form = [
+        ("userName", credentials.user_name.clone()),
+        ("password", credentials.password.clone()),
+        // newer servers check this instead of the plain password
+        ("gjp2", encode_gjp2(&credentials.password)),
+        ("udid", udid.clone()),
+        ("secret", profile.account_secret.clone()),
+    ];
+    let (account_id, user_id) = match post(transport, &profile.endpoints.login, &form)? {
+        Some(reply) => parse_login_reply(&reply)?,
+        None => return Err(ServerError::Login(LoginError::WrongCredentials).into()),
+    };
+    
+    Ok(Session {
+        user_name: credentials.user_name.clone(),
+        account_id,
+        user_id,
+        udid,
+        gjp: encode_gjp(&credentials.password),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::server::MemoryTransport;
+    use crate::codec::server::profile::LOGIN_ENDPOINT;
+    use crate::errors::Error;
+    
+    #[test]
+    fn login_reply() {
+        let transport = MemoryTransport::new().respond(LOGIN_ENDPOINT, "71,16");
+        let credentials = Credentials::new("reuploader", "hunter2");
+        let session = login(&transport, &ServerProfile::gdps19("http://localhost/gdps"), &credentials).unwrap();
+        assert_eq!((session.account_id, session.user_id), (71, 16));
+        assert_eq!(session.gjp(), "W0JbRlNBBQ==");
+        assert_eq!(session.udid, credentials.udid());
+        assert!(session.udid.starts_with('S') && session.udid.len() == 11);
+        
+        let request = &transport.requests()[0];
+        assert_eq!(request.field("userName"), Some("reuploader"));
+        assert_eq!(request.field("secret"), Some("Wmfv3899gc9"));
+        
+        let failures = [
+            ("-12", ServerError::Login(LoginError::Banned)),
+            ("-13", ServerError::Login(LoginError::Unactivated)),
+            ("-5", ServerError::Rejected(-5)),
+        ];
+        for (reply, error) in failures {
+            let transport = MemoryTransport::new().respond(LOGIN_ENDPOINT, reply);
+            let result = login(&transport, &ServerProfile::official(), &credentials);
+            assert!(matches!(result, Err(Error::Server(e)) if e == error));
+        }
+        assert_eq!(parse_login_reply("71"), Err(ServerError::Malformed("71".to_string())));
+        assert!(!format!("{credentials:?}").contains("hunter2"));
+    }
+    
+    #[test]
+    fn credentials_file() {
+        let path = env::temp_dir().join("ef19-credentials-test.json");
+        fs::write(&path, r#"{"username": "reuploader", "password": "hunter2", "udid": "S1234"}"#).unwrap();
+        let credentials = Credentials::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(credentials.user_name, "reuploader");
+        assert_eq!(credentials.udid(), "S1234");
+        assert!(Credentials::from_file(env::temp_dir().join("ef19-missing.json")).is_err());
+    }
+}
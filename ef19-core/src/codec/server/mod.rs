@@ -0,0 +1,61 @@
+// talking to boomlings style servers, everything here takes a GdTransport
+// so it can run against a real server or canned responses
+use std::fmt;
+use crate::errors::EResult;
+
+pub mod transport;
+pub mod profile;
+pub mod level;
+pub mod download;
+pub mod search;
+pub mod song;
+pub mod upload;
+pub mod login;
+
+pub use transport::{GdTransport, MemoryTransport, RecordedRequest, TransportError};
+#[cfg(feature = "http")]
+pub use transport::HttpTransport;
+pub use profile::{Endpoints, ServerProfile, ServerProfiles};
+pub use level::LevelInfo;
+pub use download::download_level;
+pub use search::{search_levels, Creator, PageInfo, SearchQuery, SearchResults, SearchType};
+pub use song::{add_song, map_level_song, map_song, song_info, SongInfo, SongMapping};
+pub use upload::{upload_level, RetryPolicy, UploadOptions, Uploaded};
+pub use login::{login, Credentials, LoginError, Session};
+
+// error when the server answered but not with what we asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerError {
+    // -1, the catch all for missing levels and refused requests
+    NotFound,
+    // a negative reply, which ones exist depends on the endpoint and server
+    Rejected(i32),
+    // the reply didn't look like anything the endpoint sends
+    Malformed(String),
+    Login(LoginError),
+    // refused before sending, the profile says the server won't take this many objects
+    ObjectLimit { count: u32, limit: u32 },
+}
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "server replied -1 (not found)"),
+            Self::Rejected(code) => write!(f, "server refused the request ({code})"),
+            Self::Malformed(reply) => write!(f, "unexpected reply from server: {reply}"),
+            Self::Login(e) => write!(f, "couldn't log in: {e}"),
+            Self::ObjectLimit { count, limit } => write!(f, "level has {count} objects, the server takes at most {limit}"),
+        }
+    }
+}
+impl std::error::Error for ServerError {}
+
+// posts the form and trims the reply, None when the server said -1
+pub(crate) fn post<T: GdTransport>(transport: &T, endpoint: &str, form: &[(&str, String)]) -> EResult<Option<String>> {
+    let form: Vec<(&str, &str)> = form.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    let response = transport.post(endpoint, &form)?;
+    let response = response.trim();
+    if response == "-1" {
+        return Ok(None);
+    }
+    Ok(Some(response.to_string()))
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use crate::errors::{EResult, KeyError};
+
+// the secret every boomlings style endpoint wants outside of accounts
+pub const COMMON_SECRET: &str = "Wmfd2893gb7";
+// and the one for logging in and other account endpoints
+pub const ACCOUNT_SECRET: &str = "Wmfv3899gc9";
+// profiles in a config file start from this one unless they say otherwise
+pub const DEFAULT_BASE_PROFILE: &str = "gdps19";
+
+pub(crate) const DOWNLOAD_ENDPOINT: &str = "downloadGJLevel22.php";
+pub(crate) const UPLOAD_ENDPOINT: &str = "uploadGJLevel19.php";
+pub(crate) const LOGIN_ENDPOINT: &str = "loginGJAccount.php";
+pub(crate) const SEARCH_ENDPOINT: &str = "getGJLevels21.php";
+pub(crate) const SONG_INFO_ENDPOINT: &str = "getGJSongInfo.php";
+// not a game endpoint, the song reupload tool most 1.9 private servers ship with
+pub(crate) const SONG_ADD_ENDPOINT: &str = "tools/songAdd.php";
+
+// relative to the profile's url, mirrors sometimes rename them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub download: String,
+    pub upload: String,
+    pub login: String,
+    pub search: String,
+    pub song_info: String,
+    pub song_add: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            download: DOWNLOAD_ENDPOINT.to_string(),
+            upload: UPLOAD_ENDPOINT.to_string(),
+            login: LOGIN_ENDPOINT.to_string(),
+            search: SEARCH_ENDPOINT.to_string(),
+            song_info: SONG_INFO_ENDPOINT.to_string(),
+            song_add: SONG_ADD_ENDPOINT.to_string(),
+        }
+    }
+}
+
+// which server to talk to and what game version to pretend to be
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerProfile {
+    pub name: String,
+    // everything before the endpoint, e.g. "https://www.boomlings.com/database"
+    pub url: String,
+    pub endpoints: Endpoints,
+    pub game_version: u32,
+    pub binary_version: u32,
+    pub secret: String,
+    pub account_secret: String,
+    // some old private servers only load plain level strings
+    pub compress_level: bool,
+    // uploads with more objects than this get refused before they're sent
+    pub max_objects: Option<u32>,
+}
+
+impl ServerProfile {
+    pub fn official() -> ServerProfile {
+        ServerProfile {
+            name: "official".to_string(),
+            url: "https://www.boomlings.com/database".to_string(),
+            endpoints: Endpoints::default(),
+            game_version: 22,
+            binary_version: 42,
+            secret: COMMON_SECRET.to_string(),
+            account_secret: ACCOUNT_SECRET.to_string(),
+            compress_level: true,
+            max_objects: None,
+        }
+    }
+    
+    // a private server running 1.9, url is everything before the endpoint
+    pub fn gdps19(url: &str) -> ServerProfile {
+        ServerProfile {
+            name: "gdps19".to_string(),
+            url: url.trim_end_matches('/').to_string(),
+            endpoints: Endpoints::default(),
+            game_version: 19,
+            binary_version: 27,
+            secret: COMMON_SECRET.to_string(),
+            account_secret: ACCOUNT_SECRET.to_string(),
+            compress_level: true,
+            max_objects: Some(40000),
+        }
+    }
+    
+    // fields every request starts with
+    pub(crate) fn base_form(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("gameVersion", self.game_version.to_string()),
+            ("binaryVersion", self.binary_version.to_string()),
+            ("secret", self.secret.clone()),
+        ]
+    }
+}
+
+// a profile as written in a config file, anything left out comes from the base profile
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileConfig {
+    base: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    endpoints: EndpointsConfig,
+    game_version: Option<u32>,
+    binary_version: Option<u32>,
+    secret: Option<String>,
+    account_secret: Option<String>,
+    compress_level: Option<bool>,
+    max_objects: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EndpointsConfig {
+    download: Option<String>,
+    upload: Option<String>,
+    login: Option<String>,
+    search: Option<String>,
+    song_info: Option<String>,
+    song_add: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    servers: BTreeMap<String, ProfileConfig>,
+}
+
+impl ProfileConfig {
+    fn apply(self, name: &str, base: &ServerProfile) -> ServerProfile {
+        let base = base.clone();
+        ServerProfile {
+            name: name.to_string(),
+            url: self.url.map_or(base.url, |url| url.trim_end_matches('/').to_string()),
+            endpoints: Endpoints {
+                download: self.endpoints.download.unwrap_or(base.endpoints.download),
+                upload: self.endpoints.upload.unwrap_or(base.endpoints.upload),
+                login: self.endpoints.login.unwrap_or(base.endpoints.login),
+                search: self.endpoints.search.unwrap_or(base.endpoints.search),
+                song_info: self.endpoints.song_info.unwrap_or(base.endpoints.song_info),
+                song_add: self.endpoints.song_add.unwrap_or(base.endpoints.song_add),
+            },
+            game_version: self.game_version.unwrap_or(base.game_version),
+            binary_version: self.binary_version.unwrap_or(base.binary_version),
+            secret: self.secret.unwrap_or(base.secret),
+            account_secret: self.account_secret.unwrap_or(base.account_secret),
+            compress_level: self.compress_level.unwrap_or(base.compress_level),
+            max_objects: self.max_objects.or(base.max_objects),
+        }
+    }
+}
+
+// named profiles, the built in ones plus whatever a config file adds or overrides
+#[derive(Debug, Clone)]
+pub struct ServerProfiles {
+    profiles: BTreeMap<String, ServerProfile>,
+}
+
+impl Default for ServerProfiles {
+    fn default() -> Self {
+        ServerProfiles::builtin()
+    }
+}
+
+impl ServerProfiles {
+    // the generic 1.9 one only points at localhost, a config file should give it a real url
+    pub fn builtin() -> ServerProfiles {
+        let profiles = [ServerProfile::official(), ServerProfile::gdps19("http://localhost/database")];
+        ServerProfiles {
+            profiles: profiles.into_iter().map(|profile| (profile.name.clone(), profile)).collect(),
+        }
+    }
+    
+    // toml, or json if the file ends in .json
+    pub fn load(path: impl AsRef<Path>) -> EResult<ServerProfiles> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            ServerProfiles::from_json(&text)
+        } else {
+            ServerProfiles::from_toml(&text)
+        }
+    }
+    
+    pub fn from_toml(text: &str) -> EResult<ServerProfiles> {
+        ServerProfiles::from_config(toml::from_str(text)?)
+    }
+    
+    pub fn from_json(text: &str) -> EResult<ServerProfiles> {
+        ServerProfiles::from_config(serde_json::from_str(text)?)
+    }
+    
+    fn from_config(config: ConfigFile) -> EResult<ServerProfiles> {
+        let mut profiles = ServerProfiles::builtin();
+        // bases have to be built in or sort before the profile using them
+        for (name, profile) in config.servers {
+            let base_name = profile.base.clone().unwrap_or_else(|| {
+                if profiles.get(&name).is_some() { name.clone() } else { DEFAULT_BASE_PROFILE.to_string() }
+            });
+            let base = profiles.get(&base_name)
+                .ok_or_else(|| KeyError::Invalid { key: format!("servers.{name}.base"), val: base_name.clone() })?;
+            let profile = profile.apply(&name, base);
+            profiles.profiles.insert(name, profile);
+        }
+        Ok(profiles)
+    }
+    
+    pub fn get(&self, name: &str) -> Option<&ServerProfile> {
+        self.profiles.get(name)
+    }
+    
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn config() {
+        let profiles = ServerProfiles::from_toml(r#"
+            [servers.gdps19]
+            url = "https://gdps.example.com/database/"
+            
+            [servers.mirror]
+            base = "official"
+            url = "https://mirror.example.com"
+            endpoints.download = "dl.php"
+            endpoints.song_add = "api/songs.php"
+            
+            [servers.old]
+            url = "http://old.example.com"
+            compress_level = false
+            max_objects = 20000
+        "#).unwrap();
+        
+        assert_eq!(profiles.names().collect::<Vec<_>>(), vec!["gdps19", "mirror", "official", "old"]);
+        let gdps = profiles.get("gdps19").unwrap();
+        assert_eq!(gdps.url, "https://gdps.example.com/database");
+        assert_eq!(gdps.game_version, 19);
+        
+        let mirror = profiles.get("mirror").unwrap();
+        assert_eq!((mirror.game_version, mirror.max_objects), (22, None));
+        assert_eq!(mirror.endpoints.download, "dl.php");
+        assert_eq!(mirror.endpoints.upload, "uploadGJLevel19.php");
+        assert_eq!(mirror.endpoints.song_add, "api/songs.php");
+        
+        // gets its defaults from the configured gdps19 above
+        let old = profiles.get("old").unwrap();
+        assert!(!old.compress_level);
+        assert_eq!((old.game_version, old.max_objects), (19, Some(20000)));
+        assert_eq!(old.secret, COMMON_SECRET);
+        
+        let json = ServerProfiles::from_json(r#"{"servers": {"other": {"url": "http://other", "game_version": 18}}}"#).unwrap();
+        assert_eq!(json.get("other").unwrap().game_version, 18);
+        assert!(ServerProfiles::from_toml("[servers.bad]\nbase = \"nowhere\"").is_err());
+        assert!(ServerProfiles::from_toml("[servers.bad]\nurl_typo = \"http://\"").is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use crate::codec::deserialise_kv;
+use crate::codec::server::{post, GdTransport, ServerError, ServerProfile};
+use crate::codec::server::level::{LevelInfo, ID_KEY};
+use crate::codec::server::song::SongInfo;
+use crate::errors::EResult;
+
+// what the type field asks for, the query is ignored by most of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchType {
+    // by name, or by id if the query is a number
+    #[default]
+    Search,
+    MostDownloaded,
+    MostLiked,
+    Trending,
+    Recent,
+    // levels by the user id in the query
+    User,
+    Featured,
+    Magic,
+    Awarded,
+    // anything else the server knows about
+    Other(u8),
+}
+
+impl SearchType {
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Search => 0,
+            Self::MostDownloaded => 1,
+            Self::MostLiked => 2,
+            Self::Trending => 3,
+            Self::Recent => 4,
+            Self::User => 5,
+            Self::Featured => 6,
+            Self::Magic => 7,
+            Self::Awarded => 11,
+            Self::Other(code) => code,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub query: String,
+    pub kind: SearchType,
+    // starts at 0
+    pub page: u32,
+}
+
+impl SearchQuery {
+    pub fn new(query: &str) -> SearchQuery {
+        SearchQuery { query: query.to_string(), ..SearchQuery::default() }
+    }
+}
+
+// an entry of the creators segment, userID:name:accountID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Creator {
+    pub user_id: u32,
+    pub name: String,
+    // 0 for creators without an account
+    pub account_id: u32,
+}
+
+impl Creator {
+    fn from_server_string(string: &str) -> Option<Creator> {
+        let mut parts = string.split(':');
+        Some(Creator {
+            user_id: parts.next()?.parse().ok()?,
+            name: parts.next()?.to_string(),
+            account_id: parts.next().and_then(|id| id.parse().ok()).unwrap_or(0),
+        })
+    }
+}
+
+// total:offset:amount
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageInfo {
+    pub total: u32,
+    pub offset: u32,
+    pub per_page: u32,
+}
+
+impl PageInfo {
+    fn from_server_string(string: &str) -> Option<PageInfo> {
+        let mut parts = string.split(':').map(|part| part.parse().ok());
+        Some(PageInfo { total: parts.next()??, offset: parts.next()??, per_page: parts.next()??.max(1) })
+    }
+    
+    pub fn has_next(&self) -> bool {
+        self.offset.saturating_add(self.per_page) < self.total
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchResults {
+    pub levels: Vec<LevelInfo>,
+    pub creators: Vec<Creator>,
+    pub songs: Vec<SongInfo>,
+    pub page: PageInfo,
+}
+
+impl SearchResults {
+    pub fn creator(&self, level: &LevelInfo) -> Option<&Creator> {
+        self.creators.iter().find(|creator| creator.user_id == level.user_id)
+    }
+    
+    pub fn song(&self, id: u32) -> Option<&SongInfo> {
+        self.songs.iter().find(|song| song.id == id)
+    }
+}
+
+// levels#creators#songs#page info#hash, levels and creators are split by |
+struct SearchReply<'a> {
+    levels: Vec<HashMap<String, String>>,
+    creators: &'a str,
+    songs: &'a str,
+    page: &'a str,
+}
+
+impl SearchReply<'_> {
+    fn parse(reply: &str) -> SearchReply<'_> {
+        let mut segments = reply.split('#');
+        let levels = segments.next().unwrap_or_default();
+        SearchReply {
+            levels: levels.split('|')
+                .filter(|level| !level.is_empty())
+                .map(|level| deserialise_kv(level, ":"))
+                .collect(),
+            creators: segments.next().unwrap_or_default(),
+            songs: segments.next().unwrap_or_default(),
+            page: segments.next().unwrap_or_default(),
+        }
+    }
+}
+
+// the raw reply, None if nothing matched
+fn post_search<T: GdTransport>(transport: &T, profile: &ServerProfile, query: &SearchQuery) -> EResult<Option<String>> {
+    let mut form = profile.base_form();
+    form.push(("type", query.kind.code().to_string()));
+    form.push(("str", query.query.clone()));
+    form.push(("page", query.page.to_string()));
+    post(transport, &profile.endpoints.search, &form)
+}
+
+// one page of results, an empty page when the server has nothing
+pub fn search_levels<T: GdTransport>(transport: &T, profile: &ServerProfile, query: &SearchQuery) -> EResult<SearchResults> {
+    let Some(response) = post_search(transport, profile, query)? else {
+        return Ok(SearchResults::default());
+    };
+    let reply = SearchReply::parse(&response);
+    // one broken level or song isn't worth losing the rest of the page over
+    let levels: Vec<LevelInfo> = reply.levels.iter()
+        .filter_map(|map| LevelInfo::from_server_map(map).ok())
+        .collect();
+    let songs = reply.songs.split("~:~")
+        .filter_map(|song| SongInfo::from_server_string(song).ok())
+        .collect();
+    // a broken creator only costs that level its creator name
+    let creators = reply.creators.split('|')
+        .filter_map(Creator::from_server_string)
+        .collect();
+    let page = match reply.page {
+        "" => PageInfo { total: levels.len() as u32, offset: 0, per_page: levels.len().max(1) as u32 },
+        page => PageInfo::from_server_string(page).ok_or_else(|| ServerError::Malformed(page.to_string()))?,
+    };
+    
+    Ok(SearchResults {
+        levels,
+        creators,
+        songs,
+        page,
+    })
+}
+
+// the search entry for one level, None if the server doesn't know it
+pub(crate) fn find_level_map<T: GdTransport>(transport: &T, profile: &ServerProfile, id: u32) -> EResult<Option<HashMap<String, String>>> {
+    let Some(response) = post_search(transport, profile, &SearchQuery::new(&id.to_string()))? else {
+        return Ok(None);
+    };
+    let id = id.to_string();
+    Ok(SearchReply::parse(&response).levels.into_iter()
+        .find(|map| map.get(ID_KEY) == Some(&id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::server::profile::SEARCH_ENDPOINT;
+    use crate::codec::server::MemoryTransport;
+    use crate::models::level::Song;
+    
+    #[test]
+    fn search() {
+        let reply = "1:128:2:1st level:3:bXkgbGV2ZWw=:5:2:6:16:10:1500:13:21:14:-3:15:2:12:4:35:0:45:120\
+            |1:129:2:theory:6:4:13:20:12:0:35:546561:45:800\
+            |2:no id:6:16\
+            #16:RobTop:71|4:Someone:0\
+            #1~|~546561~|~2~|~Ultimate Destruction~|~4~|~TMM43~|~5~|~7.49~|~10~|~http%3A%2F%2Fsong\
+            ~:~2~|~no id\
+            #25:10:10#hash";
+        let transport = MemoryTransport::new().respond(SEARCH_ENDPOINT, reply);
+        let query = SearchQuery { kind: SearchType::MostLiked, page: 1, ..SearchQuery::new("level") };
+        let results = search_levels(&transport, &ServerProfile::official(), &query).unwrap();
+        
+        let request = &transport.requests()[0];
+        assert_eq!((request.field("type"), request.field("str"), request.field("page")), (Some("2"), Some("level"), Some("1")));
+        
+        assert_eq!(results.levels.len(), 2);
+        let first = &results.levels[0];
+        assert_eq!((first.id, first.name.as_str(), first.description.as_str()), (128, "1st level", "my level"));
+        assert_eq!((first.downloads, first.likes, first.object_count), (1500, -3, 120));
+        assert_eq!(first.song, Song::Official(4));
+        assert_eq!(results.creator(first).map(|c| (c.name.as_str(), c.account_id)), Some(("RobTop", 71)));
+        
+        let second = &results.levels[1];
+        assert_eq!(results.creator(second).unwrap().account_id, 0);
+        assert_eq!(second.song, Song::Custom(546561));
+        assert_eq!(results.song(546561).unwrap().url, "http://song");
+        assert_eq!(results.songs.len(), 1);
+        assert_eq!(results.page, PageInfo { total: 25, offset: 10, per_page: 10 });
+        assert!(results.page.has_next());
+        
+        let transport = MemoryTransport::new().respond(SEARCH_ENDPOINT, "-1");
+        let results = search_levels(&transport, &ServerProfile::official(), &SearchQuery::new("nothing")).unwrap();
+        assert!(results.levels.is_empty() && !results.page.has_next());
+        
+        let transport = MemoryTransport::new().respond(SEARCH_ENDPOINT, "1:5:6:3|1:6:6:4#broken|4:Someone:0#");
+        let results = search_levels(&transport, &ServerProfile::official(), &SearchQuery::new("5")).unwrap();
+        assert_eq!(results.levels.len(), 2);
+        assert!(results.creator(&results.levels[0]).is_none());
+        assert_eq!(results.creator(&results.levels[1]).unwrap().name, "Someone");
+        
+        let transport = MemoryTransport::new().respond(SEARCH_ENDPOINT, "1:5#x:Someone:2#");
+        assert!(search_levels(&transport, &ServerProfile::official(), &SearchQuery::new("5")).unwrap().creators.is_empty());
+        assert!(!PageInfo { total: u32::MAX, offset: u32::MAX, per_page: 10 }.has_next());
+    }
+}
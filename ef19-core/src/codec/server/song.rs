@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::codec::deserialise_kv;
+use crate::codec::server::{post, GdTransport, ServerError, ServerProfile};
+use crate::models::level::{Level, Song};
+use crate::errors::{EResult, KeyError};
+
+// keys in the 1~|~x~|~2~|~x strings songs come as
+const ID_KEY: &str = "1";
+const NAME_KEY: &str = "2";
+const ARTIST_KEY: &str = "4";
+const SIZE_KEY: &str = "5";
+const URL_KEY: &str = "10";
+
+// a custom song as the server describes it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SongInfo {
+    pub id: u32,
+    pub name: String,
+    pub artist: String,
+    // in megabytes
+    pub size: f32,
+    pub url: String,
+}
+
+// the url comes percent encoded, only ascii escapes show up in practice
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| input.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+impl SongInfo {
+    pub fn from_server_string(string: &str) -> EResult<SongInfo> {
+        SongInfo::from_server_map(&deserialise_kv(string, "~|~"))
+    }
+    
+    pub fn from_server_map(map: &HashMap<String, String>) -> EResult<SongInfo> {
+        let id = map.get(ID_KEY)
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| KeyError::Missing { key: ID_KEY.to_string() })?;
+        Ok(SongInfo {
+            id,
+            name: map.get(NAME_KEY).cloned().unwrap_or_default(),
+            artist: map.get(ARTIST_KEY).cloned().unwrap_or_default(),
+            size: map.get(SIZE_KEY).and_then(|size| size.parse().ok()).unwrap_or(0.0),
+            url: map.get(URL_KEY).map(|url| percent_decode(url)).unwrap_or_default(),
+        })
+    }
+    
+    // the songs segment of a search reply, songs are split by ~:~
+    pub fn list_from_server_string(string: &str) -> EResult<Vec<SongInfo>> {
+        string.split("~:~")
+            .filter(|song| !song.is_empty())
+            .map(SongInfo::from_server_string)
+            .collect()
+    }
+}
+
+// a negative code, or the song on its own (no ~:~ split)
+fn parse_code(reply: &str) -> Result<(), ServerError> {
+    match reply.parse::<i32>() {
+        Ok(code) if code < 0 => Err(ServerError::Rejected(code)),
+        _ => Ok(()),
+    }
+}
+
+// looks a custom song up on the server a level came from
+pub fn song_info<T: GdTransport>(transport: &T, profile: &ServerProfile, id: u32) -> EResult<SongInfo> {
+    let mut form = profile.base_form();
+    form.push(("songID", id.to_string()));
+    let reply = post(transport, &profile.endpoints.song_info, &form)?
+        .ok_or(ServerError::NotFound)?;
+    parse_code(&reply)?;
+    SongInfo::from_server_string(&reply)
+}
+
+// what the tool puts in front of the new id when it worked
+const SONG_ADD_MARKER: &str = "Song reuploaded:";
+
+// the tool answers with a bit of html around the new id, or a negative code.
+// only the number right after the marker counts, error pages have numbers in them too
+fn parse_song_add_reply(reply: &str) -> Result<u32, ServerError> {
+    parse_code(reply)?;
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in reply.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    let malformed = || ServerError::Malformed(reply.to_string());
+    let (_, after) = text.split_once(SONG_ADD_MARKER).ok_or_else(malformed)?;
+    let number = after.trim_start();
+    let end = number.find(|c: char| c != '-' && !c.is_ascii_digit()).unwrap_or(number.len());
+    match number[..end].parse::<i64>() {
+        Ok(id) if id > 0 && id <= u32::MAX as i64 => Ok(id as u32),
+        Ok(code) if code < 0 && code >= i32::MIN as i64 => Err(ServerError::Rejected(code as i32)),
+        _ => Err(malformed()),
+    }
+}
+
+// registers the song's download url with a private server, gives back the id it got there
+pub fn add_song<T: GdTransport>(transport: &T, profile: &ServerProfile, song: &SongInfo) -> EResult<u32> {
+    if song.url.is_empty() {
+        return Err(KeyError::Missing { key: format!("song {} url", song.id) }.into());
+    }
+    let reply = post(transport, &profile.endpoints.song_add, &[("songlink", song.url.clone())])?
+        .ok_or(ServerError::NotFound)?;
+    Ok(parse_song_add_reply(&reply)?)
+}
+
+// source song id to target song id, kept between runs so songs only get registered once
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SongMapping {
+    songs: BTreeMap<u32, u32>,
+}
+
+impl SongMapping {
+    pub fn new() -> SongMapping {
+        SongMapping::default()
+    }
+    
+    // json like {"546561": 5000012}, a file that isn't there yet is an empty mapping
+    pub fn load(path: impl AsRef<Path>) -> EResult<SongMapping> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(serde_json::from_str(&text)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(SongMapping::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    pub fn save(&self, path: impl AsRef<Path>) -> EResult<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    pub fn get(&self, source: u32) -> Option<u32> {
+        self.songs.get(&source).copied()
+    }
+    
+    pub fn insert(&mut self, source: u32, target: u32) {
+        self.songs.insert(source, target);
+    }
+    
+    pub fn len(&self) -> usize {
+        self.songs.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.songs.is_empty()
+    }
+}
+
+// the target's id for a source song, looked up on the source and added to the target
+// the first time it's seen. the new id only gets remembered once the target knows it
+pub fn map_song<S: GdTransport, T: GdTransport>(
+    source: (&S, &ServerProfile),
+    target: (&T, &ServerProfile),
+    mapping: &mut SongMapping,
+    id: u32,
+) -> EResult<u32> {
+    if let Some(mapped) = mapping.get(id) {
+        return Ok(mapped);
+    }
+    let song = song_info(source.0, source.1, id)?;
+    let mapped = add_song(target.0, target.1, &song)?;
+    let added = song_info(target.0, target.1, mapped)?;
+    if added.id != mapped {
+        return Err(ServerError::Malformed(format!("asked for song {mapped}, got {}", added.id)).into());
+    }
+    mapping.insert(id, mapped);
+    Ok(mapped)
+}
+
+// points the level at the target's copy of its custom song, official songs are left alone
+pub fn map_level_song<S: GdTransport, T: GdTransport>(
+    level: &mut Level,
+    source: (&S, &ServerProfile),
+    target: (&T, &ServerProfile),
+    mapping: &mut SongMapping,
+) -> EResult<()> {
+    if let Song::Custom(id) = *level.song() {
+        let mapped = map_song(source, target, mapping, id)?;
+        level.set_song(Song::Custom(mapped));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use crate::codec::server::MemoryTransport;
+    use crate::codec::server::profile::{SONG_ADD_ENDPOINT, SONG_INFO_ENDPOINT};
+    use crate::errors::Error;
+    
+    #[test]
+    fn song_info() {
+        let song = SongInfo::from_server_string(
+            "1~|~546561~|~2~|~Ultimate Destruction~|~3~|~13~|~4~|~TMM43~|~5~|~7.49~|~6~|~~|~10~|~https%3A%2F%2Faudio.ngfiles.com%2F546000%2F546561_Ultimate.mp3~|~7~|~~|~8~|~1"
+        ).unwrap();
+        assert_eq!(song.id, 546561);
+        assert_eq!((song.name.as_str(), song.artist.as_str()), ("Ultimate Destruction", "TMM43"));
+        assert_eq!(song.size, 7.49);
+        assert_eq!(song.url, "https://audio.ngfiles.com/546000/546561_Ultimate.mp3");
+        
+        let songs = SongInfo::list_from_server_string("1~|~1~|~2~|~a~:~1~|~2~|~2~|~b").unwrap();
+        assert_eq!(songs.iter().map(|s| s.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(SongInfo::list_from_server_string("").unwrap().is_empty());
+        assert!(SongInfo::from_server_string("2~|~no id").is_err());
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+    
+    #[test]
+    fn mapping() {
+        let source = MemoryTransport::new()
+            .respond(SONG_INFO_ENDPOINT, "1~|~546561~|~2~|~Ultimate Destruction~|~4~|~TMM43~|~5~|~7.49~|~10~|~http%3A%2F%2Fsong.mp3");
+        let target = MemoryTransport::new()
+            .respond(SONG_ADD_ENDPOINT, "<b>Song reuploaded:</b> 5000012<hr>")
+            .respond(SONG_INFO_ENDPOINT, "1~|~5000012~|~2~|~Ultimate Destruction~|~10~|~http%3A%2F%2Fsong.mp3");
+        let (official, gdps) = (ServerProfile::official(), ServerProfile::gdps19("http://localhost/gdps"));
+        
+        let mut mapping = SongMapping::new();
+        mapping.insert(1, 7);
+        assert_eq!(map_song((&source, &official), (&target, &gdps), &mut mapping, 1).unwrap(), 7);
+        assert!(source.requests().is_empty());
+        
+        assert_eq!(map_song((&source, &official), (&target, &gdps), &mut mapping, 546561).unwrap(), 5000012);
+        assert_eq!(source.requests()[0].field("songID"), Some("546561"));
+        assert_eq!(target.requests()[0].field("songlink"), Some("http://song.mp3"));
+        assert_eq!(target.requests()[1].field("songID"), Some("5000012"));
+        // cached, nothing gets registered twice
+        map_song((&source, &official), (&target, &gdps), &mut mapping, 546561).unwrap();
+        assert_eq!((source.requests().len(), target.requests().len()), (1, 2));
+        
+        let path = env::temp_dir().join("ef19-song-mapping-test.json");
+        mapping.save(&path).unwrap();
+        let loaded = SongMapping::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, mapping);
+        assert!(SongMapping::load(&path).unwrap().is_empty());
+        
+        let refused = MemoryTransport::new().respond(SONG_ADD_ENDPOINT, "-3");
+        let result = map_song((&source, &official), (&refused, &gdps), &mut SongMapping::new(), 546561);
+        assert!(matches!(result, Err(Error::Server(ServerError::Rejected(-3)))));
+        
+        // the tool said yes but the server doesn't have it, so it isn't remembered
+        let missing = MemoryTransport::new()
+            .respond(SONG_ADD_ENDPOINT, "<b>Song reuploaded:</b> 5000013<hr>")
+            .respond(SONG_INFO_ENDPOINT, "-1");
+        let mut mapping = SongMapping::new();
+        assert!(map_song((&source, &official), (&missing, &gdps), &mut mapping, 546561).is_err());
+        assert!(mapping.is_empty());
+        
+        assert_eq!(parse_song_add_reply("no id here"), Err(ServerError::Malformed("no id here".to_string())));
+        let error_page = "<b>Error:</b> only 2 songs a day, try again in 14 hours";
+        assert_eq!(parse_song_add_reply(error_page), Err(ServerError::Malformed(error_page.to_string())));
+        assert_eq!(parse_song_add_reply("<b>Song reuploaded:</b> -4<hr>"), Err(ServerError::Rejected(-4)));
+    }
+}
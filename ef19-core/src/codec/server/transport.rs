@@ -0,0 +1,173 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+// error when a request didn't get a response body back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportError {
+    // dns lookup or connecting failed, nothing reached the server
+    Unreachable(String),
+    // timed out or dropped after the request went out, body wasn't text...
+    Connection(String),
+    // the server answered with a non-2xx status
+    Status(u16),
+    // in-memory transport had no response set up for the endpoint
+    NoResponse(String),
+}
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unreachable(e) => write!(f, "couldn't reach the server: {e}"),
+            Self::Connection(e) => write!(f, "connection error: {e}"),
+            Self::Status(code) => write!(f, "server returned status {code}"),
+            Self::NoResponse(endpoint) => write!(f, "no response set up for {endpoint}"),
+        }
+    }
+}
+impl std::error::Error for TransportError {}
+
+// posts a form to a gd style endpoint (e.g. "downloadGJLevel22.php") and gives back the raw body,
+// working out what the body means is up to the caller since every endpoint does it differently
+pub trait GdTransport {
+    fn post(&self, endpoint: &str, form: &[(&str, &str)]) -> Result<String, TransportError>;
+}
+
+impl<T: GdTransport + ?Sized> GdTransport for &T {
+    fn post(&self, endpoint: &str, form: &[(&str, &str)]) -> Result<String, TransportError> {
+        (**self).post(endpoint, form)
+    }
+}
+
+// blocking transport against a real server, base_url is everything before the endpoint
+// (e.g. "https://www.boomlings.com/database")
+#[cfg(feature = "http")]
+pub struct HttpTransport {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+#[cfg(feature = "http")]
+impl HttpTransport {
+    pub fn new(base_url: &str) -> HttpTransport {
+        HttpTransport {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(30))
+                .build(),
+        }
+    }
+    
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+#[cfg(feature = "http")]
+impl GdTransport for HttpTransport {
+    fn post(&self, endpoint: &str, form: &[(&str, &str)]) -> Result<String, TransportError> {
+        let url = format!("{}/{}", self.base_url, endpoint);
+        // boomlings turns away anything with a user agent set
+        let request = self.agent.post(&url).set("User-Agent", "");
+        match request.send_form(form) {
+            Ok(response) => response.into_string().map_err(|e| TransportError::Connection(e.to_string())),
+            Err(ureq::Error::Status(code, _)) => Err(TransportError::Status(code)),
+            Err(ureq::Error::Transport(e)) if matches!(e.kind(), ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed) => {
+                Err(TransportError::Unreachable(e.to_string()))
+            },
+            Err(e) => Err(TransportError::Connection(e.to_string())),
+        }
+    }
+}
+
+// a request the in-memory transport was given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub endpoint: String,
+    pub form: Vec<(String, String)>,
+}
+
+impl RecordedRequest {
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.form.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+// answers from responses set up ahead of time, for tests and replaying recorded fixtures.
+// responses for an endpoint are used in order and the last one keeps getting repeated
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    responses: RefCell<HashMap<String, VecDeque<Result<String, TransportError>>>>,
+    requests: RefCell<Vec<RecordedRequest>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> MemoryTransport {
+        MemoryTransport::default()
+    }
+    
+    pub fn respond(self, endpoint: &str, body: &str) -> MemoryTransport {
+        self.push(endpoint, Ok(body.to_string()));
+        self
+    }
+    
+    pub fn fail(self, endpoint: &str, error: TransportError) -> MemoryTransport {
+        self.push(endpoint, Err(error));
+        self
+    }
+    
+    pub fn push(&self, endpoint: &str, response: Result<String, TransportError>) {
+        self.responses.borrow_mut().entry(endpoint.to_string()).or_default().push_back(response);
+    }
+    
+    // every request so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.borrow().clone()
+    }
+}
+
+impl GdTransport for MemoryTransport {
+    fn post(&self, endpoint: &str, form: &[(&str, &str)]) -> Result<String, TransportError> {
+        self.requests.borrow_mut().push(RecordedRequest {
+            endpoint: endpoint.to_string(),
+            form: form.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        });
+        
+        let mut responses = self.responses.borrow_mut();
+        let queue = responses.get_mut(endpoint).ok_or_else(|| TransportError::NoResponse(endpoint.to_string()))?;
+        match queue.len() {
+            0 => Err(TransportError::NoResponse(endpoint.to_string())),
+            1 => queue[0].clone(),
+            _ => queue.pop_front().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn fetch<T: GdTransport>(transport: T) -> Result<String, TransportError> {
+        transport.post("downloadGJLevel22.php", &[("levelID", "128"), ("secret", "Wmfd2893gb7")])
+    }
+    
+    #[test]
+    fn memory_transport() {
+        let transport = MemoryTransport::new()
+            .fail("downloadGJLevel22.php", TransportError::Status(500))
+            .respond("downloadGJLevel22.php", "1:128:2:1st level");
+        
+        assert_eq!(fetch(&transport), Err(TransportError::Status(500)));
+        assert_eq!(fetch(&transport).unwrap(), "1:128:2:1st level");
+        assert_eq!(fetch(&transport).unwrap(), "1:128:2:1st level");
+        assert_eq!(
+            transport.post("getGJLevels21.php", &[]),
+            Err(TransportError::NoResponse("getGJLevels21.php".to_string())),
+        );
+        
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].endpoint, "downloadGJLevel22.php");
+        assert_eq!(requests[0].field("levelID"), Some("128"));
+        assert_eq!(requests[0].field("gjp"), None);
+    }
+}
@@ -0,0 +1,272 @@
+use std::thread;
+use std::time::Duration;
+use crate::codec;
+use crate::codec::crypt::level_seed2;
+use crate::codec::server::{GdTransport, ServerError, ServerProfile, Session, TransportError};
+use crate::models::level::{Level, Password, Song};
+use crate::errors::{Error, EResult};
+
+// the game sends a random 10 character seed, servers only check it's there
+const SEED_LENGTH: usize = 10;
+
+// private servers rate limit uploads hard, so failed attempts wait
+// twice as long as the last one before trying again
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // including the first one
+    pub attempts: u32,
+    pub delay: Duration,
+    pub max_delay: Duration,
+    // also try again on -1, some private servers send it when rate limiting
+    // but it's what every server sends for an upload it won't take too
+    pub retry_rejected: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 5,
+            delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+            retry_rejected: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> RetryPolicy {
+        RetryPolicy { attempts: 1, ..RetryPolicy::default() }
+    }
+    
+    // wait before the given retry, 1 being the first
+    pub fn delay_before(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    pub retry: RetryPolicy,
+    // replaces the level's own password
+    pub password: Option<Password>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uploaded {
+    pub level_id: u32,
+    // how many tries it took
+    pub attempts: u32,
+}
+
+// the reply is either the new level's id or a negative error code
+pub fn parse_upload_reply(reply: &str) -> Result<u32, ServerError> {
+    let reply = reply.trim();
+    match reply.parse::<i64>() {
+        Ok(id) if id > 0 && id <= u32::MAX as i64 => Ok(id as u32),
+        Ok(code) if code < 0 && code >= i32::MIN as i64 => Err(ServerError::Rejected(code as i32)),
+        _ => Err(ServerError::Malformed(reply.to_string())),
+    }
+}
+
+// worth trying again after a wait, anything that might have reached the server
+// and been saved isn't, or the level could get uploaded twice
+fn is_retryable(error: &Error, retry: &RetryPolicy) -> bool {
+    match error {
+        Error::Transport(TransportError::Unreachable(_)) => true,
+        Error::Transport(TransportError::Status(code)) => *code == 429 || *code >= 500,
+        Error::Server(ServerError::Rejected(-1)) => retry.retry_rejected,
+        _ => false,
+    }
+}
+
+fn upload_form(
+    profile: &ServerProfile, session: &Session, level: &Level, options: &UploadOptions,
+) -> EResult<Vec<(&'static str, String)>> {
+    if let Some(limit) = profile.max_objects.filter(|&limit| level.object_count > limit) {
+        return Err(ServerError::ObjectLimit { count: level.object_count, limit }.into());
+    }
+    let level_string = match profile.compress_level {
+        true => level.object_string()?,
+        false => codec::unzip_string(&level.object_string()?)?,
+    };
+    let (audio_track, song_id) = match level.song {
+        Song::Official(id) => (id, 0),
+        Song::Custom(id) => (0, id),
+    };
+    let seed: String = level_seed2(&level_string).chars().filter(char::is_ascii_alphanumeric).take(SEED_LENGTH).collect();
+    
+    let mut form = profile.base_form();
+    form.extend([
+        ("accountID", session.account_id.to_string()),
+        ("gjp", session.gjp.clone()),
+        ("userName", session.user_name.clone()),
+        ("udid", session.udid.clone()),
+        // 0 uploads a new level instead of updating one
+        ("levelID", "0".to_string()),
+        ("levelName", level.name.clone()),
+        // 1.9 shows descriptions as they are, it doesn't know about base64 ones
+        ("levelDesc", level.description.clone()),
+        ("levelVersion", level.version.to_string()),
+        ("levelLength", level.length.to_string()),
+        ("audioTrack", audio_track.to_string()),
+        ("songID", song_id.to_string()),
+        ("auto", "0".to_string()),
+        ("password", options.password.unwrap_or(level.password).to_19()),
+        ("original", "0".to_string()),
+        ("twoPlayer", (level.is_two_player as u8).to_string()),
+        ("objects", level.object_count.to_string()),
+        ("coins", "0".to_string()),
+        ("requestedStars", "0".to_string()),
+        ("seed", seed),
+        ("seed2", level_seed2(&level_string)),
+        ("levelString", level_string),
+    ]);
+    Ok(form)
+}
+
+// levels loaded from gmd files don't carry their object count or two player
+// setting, so the metadata is worked out again before it's sent
+pub fn upload_level<T: GdTransport>(
+    transport: &T, profile: &ServerProfile, session: &Session, level: &mut Level, options: &UploadOptions,
+) -> EResult<Uploaded> {
+    level.recompute_metadata()?;
+    let retry = &options.retry;
+    let form = upload_form(profile, session, level, options)?;
+    let form: Vec<(&str, &str)> = form.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let result = transport.post(&profile.endpoints.upload, &form)
+            .map_err(Error::from)
+            .and_then(|reply| Ok(parse_upload_reply(&reply)?));
+        match result {
+            Ok(level_id) => return Ok(Uploaded { level_id, attempts }),
+            Err(e) if attempts < retry.attempts && is_retryable(&e, retry) => thread::sleep(retry.delay_before(attempts)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::server::MemoryTransport;
+    use crate::codec::server::profile::UPLOAD_ENDPOINT;
+    
+    fn level() -> Level {
+        Level {
+            name: "awawa".to_string(),
+            description: "my level".to_string(),
+            object_str: codec::zip_string("kA2,0;1,1,2,15,3,15;").unwrap(),
+            object_list: None,
+            song: Song::Custom(546561),
+            password: Password::Code(123),
+            version: 2,
+            game_version: 21,
+            binary_version: 0,
+            length: 1,
+            is_two_player: false,
+            object_count: 1,
+            has_low_detail: false,
+        }
+    }
+    
+    fn session() -> Session {
+        Session {
+            user_name: "reuploader".to_string(),
+            account_id: 71,
+            user_id: 16,
+            udid: "S1234".to_string(),
+            gjp: "W0JbRlNBBQ==".to_string(),
+        }
+    }
+    
+    fn options() -> UploadOptions {
+        UploadOptions {
+            retry: RetryPolicy { attempts: 3, delay: Duration::ZERO, max_delay: Duration::ZERO, retry_rejected: false },
+            password: None,
+        }
+    }
+    
+    #[test]
+    fn upload() {
+        let transport = MemoryTransport::new()
+            .fail(UPLOAD_ENDPOINT, TransportError::Unreachable("connection refused".to_string()))
+            .fail(UPLOAD_ENDPOINT, TransportError::Status(503))
+            .respond(UPLOAD_ENDPOINT, "4821");
+        let profile = ServerProfile::gdps19("http://localhost/gdps");
+        let session = session();
+        let uploaded = upload_level(&transport, &profile, &session, &mut level(), &options()).unwrap();
+        assert_eq!(uploaded, Uploaded { level_id: 4821, attempts: 3 });
+        
+        let request = &transport.requests()[2];
+        assert_eq!(request.endpoint, UPLOAD_ENDPOINT);
+        assert_eq!(request.field("gameVersion"), Some("19"));
+        assert_eq!(request.field("userName"), Some("reuploader"));
+        assert_eq!(request.field("gjp"), Some("W0JbRlNBBQ=="));
+        assert_eq!(request.field("accountID"), Some("71"));
+        assert_eq!(request.field("songID"), Some("546561"));
+        assert_eq!(request.field("audioTrack"), Some("0"));
+        assert_eq!(request.field("levelDesc"), Some("my level"));
+        assert_eq!(request.field("seed").map(str::len), Some(SEED_LENGTH));
+        assert!(request.field("seed2").is_some());
+        assert_eq!(request.field("password"), Some("123"));
+        
+        let options = UploadOptions { password: Some(Password::FreeCopy), ..options() };
+        upload_level(&transport, &profile, &session, &mut level(), &options).unwrap();
+        assert_eq!(transport.requests()[3].field("password"), Some("1"));
+    }
+    
+    #[test]
+    fn rejected() {
+        let transport = MemoryTransport::new().respond(UPLOAD_ENDPOINT, "-1");
+        let profile = ServerProfile::gdps19("http://localhost/gdps");
+        let session = session();
+        let result = upload_level(&transport, &profile, &session, &mut level(), &options());
+        assert!(matches!(result, Err(Error::Server(ServerError::Rejected(-1)))));
+        assert_eq!(transport.requests().len(), 1);
+        
+        let mut retry_rejected = options();
+        retry_rejected.retry.retry_rejected = true;
+        upload_level(&transport, &profile, &session, &mut level(), &retry_rejected).unwrap_err();
+        assert_eq!(transport.requests().len(), 4);
+        
+        // the request might have gone through, so it isn't sent again
+        let timed_out = MemoryTransport::new().fail(UPLOAD_ENDPOINT, TransportError::Connection("timed out".to_string()));
+        upload_level(&timed_out, &profile, &session, &mut level(), &options()).unwrap_err();
+        assert_eq!(timed_out.requests().len(), 1);
+        
+        let small = ServerProfile { max_objects: Some(0), ..profile };
+        let result = upload_level(&transport, &small, &session, &mut level(), &options());
+        assert!(matches!(result, Err(Error::Server(ServerError::ObjectLimit { count: 1, limit: 0 }))));
+        assert_eq!(transport.requests().len(), 4);
+        
+        assert_eq!(parse_upload_reply("12\n"), Ok(12));
+        assert_eq!(parse_upload_reply("-9"), Err(ServerError::Rejected(-9)));
+        assert_eq!(parse_upload_reply("<html>"), Err(ServerError::Malformed("<html>".to_string())));
+        assert_eq!(RetryPolicy::default().delay_before(3), Duration::from_secs(8));
+        assert_eq!(RetryPolicy::default().delay_before(10), Duration::from_secs(60));
+    }
+    
+    #[test]
+    fn gmd_level() {
+        let gmd = format!(
+            "<?xml version=\"1.0\"?><plist version=\"1.0\" gjver=\"2.0\"><dict><k>kCEK</k><i>4</i><k>k2</k><s>awawa</s><k>k4</k><s>{}</s></dict></plist>",
+            codec::zip_string("kA2,0,kA10,1;1,1,2,15,3,15;1,1,2,45,3,15;").unwrap(),
+        );
+        let mut level = Level::from_gmd(gmd.as_bytes()).unwrap();
+        let transport = MemoryTransport::new().respond(UPLOAD_ENDPOINT, "4822");
+        let profile = ServerProfile::gdps19("http://localhost/gdps");
+        let session = session();
+        upload_level(&transport, &profile, &session, &mut level, &options()).unwrap();
+        let request = &transport.requests()[0];
+        assert_eq!(request.field("objects"), Some("2"));
+        assert_eq!(request.field("twoPlayer"), Some("1"));
+        
+        let small = ServerProfile { max_objects: Some(1), ..profile };
+        let result = upload_level(&transport, &small, &session, &mut Level::from_gmd(gmd.as_bytes()).unwrap(), &options());
+        assert!(matches!(result, Err(Error::Server(ServerError::ObjectLimit { count: 2, limit: 1 }))));
+    }
+}
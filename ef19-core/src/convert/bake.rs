@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use crate::models::level::ObjectList;
+use crate::models::object::is_trigger;
+use crate::models::groups::GroupIndex;
+use crate::models::trigger::{Trigger, TriggerKind, MOVE_TRIGGER_ID};
+
+// move offsets are stored in a third of the units positions use (10 to a block)
+const MOVE_UNIT_SCALE: f32 = 3.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    // spawn triggered, or the trigger's own group gets spawned
+    Spawned,
+    TouchTriggered,
+    // the trigger's own group gets toggled on or off
+    Toggled,
+    // multi-trigger, or more than one move trigger targets the group
+    Repeated,
+    LockedToPlayer,
+    // moves towards another group instead of by an offset
+    MoveToTarget,
+}
+
+#[derive(Debug, Clone)]
+pub struct BakedMove {
+    pub group: u16,
+    pub x_pos: f32,
+    // offset in position units
+    pub offset: (f32, f32),
+    pub objects_moved: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkippedMove {
+    pub group: u16,
+    pub x_pos: f32,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Default)]
+pub struct BakeReport {
+    pub baked: Vec<BakedMove>,
+    pub skipped: Vec<SkippedMove>,
+}
+
+// applies move triggers that always end up in the same place directly to their group,
+// since 1.9 ignores groups and the objects would otherwise sit where they started
+pub fn bake_move_triggers(objects: &mut ObjectList) -> BakeReport {
+    let mut report = BakeReport::default();
+    
+    let index = GroupIndex::new(objects);
+    let move_count = |group| {
+        index.targeted_by(group).iter()
+            .filter(|&&i| objects.objects()[i].id() == MOVE_TRIGGER_ID)
+            .count()
+    };
+    
+    // group -> offset, and the index of every trigger that got baked
+    let mut offsets: HashMap<u16, (f32, f32)> = HashMap::new();
+    let mut baked_triggers = HashSet::new();
+    for (i, obj) in objects.objects().iter().enumerate() {
+        let Some(Trigger { activation, kind: TriggerKind::Move(movement) }) = Trigger::from_object(obj) else {
+            continue;
+        };
+        let Some(group) = movement.target_group else { continue; };
+        let own_groups = obj.groups();
+        
+        let reason = if activation.spawn_triggered || own_groups.iter().any(|&g| index.is_spawned(g)) {
+            Some(SkipReason::Spawned)
+        } else if activation.touch_triggered {
+            Some(SkipReason::TouchTriggered)
+        } else if own_groups.iter().any(|&g| index.is_toggled(g)) {
+            Some(SkipReason::Toggled)
+        } else if activation.multi_trigger || move_count(group) > 1 {
+            Some(SkipReason::Repeated)
+        } else if movement.lock_to_player.0 || movement.lock_to_player.1 {
+            Some(SkipReason::LockedToPlayer)
+        } else if movement.use_target {
+            Some(SkipReason::MoveToTarget)
+        } else {
+            None
+        };
+        
+        match reason {
+            Some(reason) => report.skipped.push(SkippedMove { group, x_pos: obj.x_pos(), reason }),
+            None => {
+                let offset = (movement.offset.0 * MOVE_UNIT_SCALE, movement.offset.1 * MOVE_UNIT_SCALE);
+                offsets.insert(group, offset);
+                baked_triggers.insert(i);
+                report.baked.push(BakedMove { group, x_pos: obj.x_pos(), offset, objects_moved: 0 });
+            },
+        }
+    }
+    if offsets.is_empty() {
+        return report;
+    }
+    
+    // triggers don't get moved, where they are decides when they fire
+    for baked in report.baked.iter_mut() {
+        let (dx, dy) = offsets[&baked.group];
+        for &i in index.members(baked.group) {
+            let obj = &mut objects.objects_mut()[i];
+            if is_trigger(obj.id()) {
+                continue;
+            }
+            obj.set_x_pos(obj.x_pos() + dx);
+            obj.set_y_pos(obj.y_pos() + dy);
+            baked.objects_moved += 1;
+        }
+    }
+    
+    // baked triggers have done their job
+    let mut i = 0;
+    objects.objects_mut().retain(|_| {
+        i += 1;
+        !baked_triggers.contains(&(i - 1))
+    });
+    
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn bakes_simple_moves() {
+        let mut objects = ObjectList::from_raw_str(
+            "kA2,0;1,1,2,300,3,15,57,2;1,1,2,330,3,15,57,2.3;1,901,2,100,3,15,51,2,28,10,29,-5,10,0.5;1,1,2,360,3,15;"
+        ).unwrap();
+        let report = bake_move_triggers(&mut objects);
+        
+        let positions: Vec<(u16, f32, f32)> = objects.objects().iter()
+            .map(|obj| (obj.id(), obj.x_pos(), obj.y_pos()))
+            .collect();
+        assert_eq!(positions, vec![(1, 330., 0.), (1, 360., 0.), (1, 360., 15.)]);
+        
+        assert_eq!(report.baked.len(), 1);
+        assert_eq!(report.baked[0].offset, (30., -15.));
+        assert_eq!(report.baked[0].objects_moved, 2);
+        assert!(report.skipped.is_empty());
+    }
+    
+    #[test]
+    fn skips_dynamic_moves() {
+        let mut objects = ObjectList::from_raw_str(concat!(
+            "kA2,0;",
+            "1,1,2,300,3,15,57,2.3.4.5;",
+            // touch triggered
+            "1,901,2,100,3,15,51,2,28,10,11,1;",
+            // two moves on the same group
+            "1,901,2,100,3,15,51,3,28,10;",
+            "1,901,2,200,3,15,51,3,28,-10;",
+            // the trigger is in a group that gets spawned
+            "1,901,2,100,3,15,51,4,28,10,57,9;",
+            "1,1268,2,50,3,15,51,9;",
+            // the trigger is in a group that gets toggled
+            "1,901,2,100,3,15,51,5,28,10,57,8;",
+            "1,1049,2,50,3,15,51,8;",
+        )).unwrap();
+        let report = bake_move_triggers(&mut objects);
+        
+        assert!(report.baked.is_empty());
+        let reasons: Vec<(u16, SkipReason)> = report.skipped.iter().map(|s| (s.group, s.reason)).collect();
+        assert_eq!(reasons, vec![
+            (2, SkipReason::TouchTriggered),
+            (3, SkipReason::Repeated),
+            (3, SkipReason::Repeated),
+            (4, SkipReason::Spawned),
+            (5, SkipReason::Toggled),
+        ]);
+        assert_eq!(objects.objects()[0].x_pos(), 300.);
+        assert_eq!(objects.objects().len(), 8);
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use crate::models::level::ObjectList;
+use crate::models::object::Color;
+use crate::models::trigger::{Trigger, TriggerKind};
+
+// main colour channel, 1.9 only has the old colour key (19)
+const MAIN_COLOR_KEY: &str = "21";
+// 899 only keys, the legacy triggers don't have them
+const COLOR_TRIGGER_ONLY_KEYS: [&str; 4] = ["23", "35", "49", "50"];
+
+#[derive(Debug, Clone)]
+pub struct ConvertedColorTrigger {
+    pub channel: u16,
+    pub legacy_id: u16,
+    pub x_pos: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct LostColorTrigger {
+    pub channel: u16,
+    pub x_pos: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct ColorReport {
+    // colour triggers rewritten as the legacy trigger for their channel
+    pub converted: Vec<ConvertedColorTrigger>,
+    // colour triggers for channels 1.9 doesn't have, these get removed
+    pub lost: Vec<LostColorTrigger>,
+    // copy colour and opacity don't exist on legacy triggers
+    pub lost_settings: usize,
+    // channel -> number of objects moved onto the old colour that matches it
+    pub remapped: BTreeMap<u16, usize>,
+    // channel -> number of objects left on the default colour
+    pub unmapped: BTreeMap<u16, usize>,
+}
+
+// moves colour triggers and object colours onto what 1.9 has
+pub fn downgrade_colors(objects: &mut ObjectList) -> ColorReport {
+    let mut report = ColorReport::default();
+    let mut remove = Vec::with_capacity(objects.len());
+    for obj in objects.iter_mut() {
+        if let Some(channel) = obj.remove_raw(MAIN_COLOR_KEY).and_then(|v| v.parse::<u16>().ok()).filter(|&c| c != 0) {
+            match Color::from_new_id(channel) {
+                Some(color) => {
+                    // the old key wins if it's already there
+                    if obj.color().is_none() {
+                        obj.set_color(Some(color));
+                    }
+                    *report.remapped.entry(channel).or_default() += 1;
+                },
+                None => { *report.unmapped.entry(channel).or_default() += 1; },
+            }
+        }
+        
+        let Some(mut trigger) = Trigger::from_object(obj) else {
+            remove.push(false);
+            continue;
+        };
+        let TriggerKind::Color(color) = &mut trigger.kind else {
+            remove.push(false);
+            continue;
+        };
+        if color.legacy_id.is_some() {
+            remove.push(false);
+            continue;
+        }
+        match color.legacy_equivalent() {
+            Some(legacy) => {
+                if color.copy_channel.is_some() || color.opacity != 1. {
+                    report.lost_settings += 1;
+                }
+                color.legacy_id = Some(legacy);
+                for key in COLOR_TRIGGER_ONLY_KEYS {
+                    obj.remove_raw(key);
+                }
+                let channel = color.channel;
+                trigger.write_to(obj);
+                report.converted.push(ConvertedColorTrigger { channel, legacy_id: legacy, x_pos: obj.x_pos() });
+                remove.push(false);
+            },
+            None => {
+                report.lost.push(LostColorTrigger { channel: color.channel, x_pos: obj.x_pos() });
+                remove.push(true);
+            },
+        }
+    }
+    
+    let mut remove = remove.into_iter();
+    objects.retain(|_| !remove.next().unwrap());
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn colors() {
+        let mut objects = ObjectList::from_raw_str(concat!(
+            "kA2,0;",
+            "1,1,2,15,3,15,21,2;",
+            "1,1,2,45,3,15,21,60;",
+            "1,899,2,0,3,0,23,1000,7,10,35,0.5;",
+            "1,899,2,30,3,0,23,3,7,20;",
+            "1,899,2,60,3,0,23,60;",
+        )).unwrap();
+        let report = downgrade_colors(&mut objects);
+        
+        assert_eq!(report.converted.len(), 2);
+        assert_eq!(report.converted[1].legacy_id, 718);
+        assert_eq!(report.lost_settings, 1);
+        assert_eq!(report.lost.len(), 1);
+        assert_eq!(report.lost[0].channel, 60);
+        assert_eq!(report.remapped, BTreeMap::from([(2, 1)]));
+        assert_eq!(report.unmapped, BTreeMap::from([(60, 1)]));
+        
+        let ids: Vec<u16> = objects.iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![1, 1, 29, 718]);
+        assert_eq!(objects.objects()[0].color(), Some(Color::Col2));
+        assert_eq!(objects.objects()[2].raw("23"), None);
+        assert_eq!(objects.objects()[2].raw("7"), Some("10"));
+    }
+}
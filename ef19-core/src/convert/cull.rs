@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use crate::models::level::ObjectList;
+use crate::models::object::{is_trigger, LevelObject, ObjectKind, ObjectVariant};
+use crate::models::groups::{GroupIndex, Visibility};
+use crate::models::trigger::{Activation, AlphaTrigger, Trigger, TriggerKind};
+use crate::models::version::GameVersion;
+
+const HIDE_KEY: &str = "135";
+// nothing gets drawn this far past the floor or the start of the level
+const FAR_BELOW: f32 = -300.;
+const FAR_LEFT: f32 = -300.;
+// the ground covers anything that doesn't reach above this
+const BELOW_FLOOR: f32 = 0.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullLevel {
+    // only removes objects that can't be seen in any version
+    #[default]
+    Conservative,
+    // also removes everything the conservative level wasn't sure about
+    Aggressive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullReason {
+    // toggled off before it's seen, never toggled on
+    StartsHidden,
+    // toggled off before it's seen, toggled on later
+    ShownByTrigger,
+    // hidden in the edit object menu
+    HiddenFlag,
+    // faded to nothing by an alpha trigger before it's seen
+    Transparent,
+    // like Transparent, but faded back in later
+    FadedIn,
+    FarBelow,
+    FarLeft,
+    BelowFloor,
+}
+
+#[derive(Debug, Clone)]
+pub struct CullCandidate {
+    pub id: u16,
+    pub x_pos: f32,
+    pub y_pos: f32,
+    pub reason: CullReason,
+}
+
+#[derive(Debug, Default)]
+pub struct CullReport {
+    pub removed: usize,
+    // objects the conservative level kept, these are removed too when aggressive
+    pub uncertain: Vec<CullCandidate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alpha {
+    Transparent,
+    FadedIn,
+}
+
+fn alpha_trigger(obj: &LevelObject) -> Option<(Activation, AlphaTrigger)> {
+    match Trigger::from_object(obj)? {
+        Trigger { activation, kind: TriggerKind::Alpha(alpha) } => Some((activation, alpha)),
+        _ => None,
+    }
+}
+
+// groups faded out by an alpha trigger before they're seen
+fn alpha_groups(objects: &ObjectList, index: &GroupIndex) -> HashMap<u16, Alpha> {
+    let mut groups = HashMap::new();
+    for (_, trigger) in objects.objects().iter().filter_map(alpha_trigger) {
+        let Some(group) = trigger.target_group else { continue; };
+        if groups.contains_key(&group) {
+            continue;
+        }
+        let leftmost = index.leftmost_member(objects, group).unwrap_or(f32::INFINITY);
+        let alphas = index.targeted_by(group).iter()
+            .map(|&i| &objects.objects()[i])
+            .filter_map(|obj| Some((obj.x_pos(), alpha_trigger(obj)?)));
+        let mut faded_out = false;
+        let mut faded_in = false;
+        for (x_pos, (activation, alpha)) in alphas {
+            if alpha.opacity > 0. {
+                faded_in = true;
+            } else if activation.fires_on_pass() && x_pos <= leftmost {
+                faded_out = true;
+            }
+        }
+        if faded_out {
+            groups.insert(group, if faded_in { Alpha::FadedIn } else { Alpha::Transparent });
+        }
+    }
+    groups
+}
+
+// why an object should go, and whether we're sure about it
+fn cull_reason(obj: &LevelObject, index: &GroupIndex, alpha: &HashMap<u16, Alpha>) -> Option<(CullReason, bool)> {
+    if is_trigger(obj.id()) {
+        return None;
+    }
+    // None means we don't know what it is, so it might be gameplay
+    let gameplay = ObjectVariant::from_id(obj.id()).map(|v| v.has_gameplay());
+    
+    // toggled off objects lose their hitbox too, so these are safe whatever they are
+    match index.object_visibility(obj) {
+        Visibility::StartsHidden => { return Some((CullReason::StartsHidden, true)); },
+        Visibility::ShownByTrigger => { return Some((CullReason::ShownByTrigger, false)); },
+        Visibility::Visible => (),
+    }
+    // move and spawn triggers bring off screen objects in, so those aren't certain
+    let targeted = obj.groups().iter().any(|&g| !index.targeted_by(g).is_empty());
+    let bounds = obj.sprite_bounds();
+    if bounds.max_y < FAR_BELOW {
+        return Some((CullReason::FarBelow, !targeted));
+    }
+    if bounds.max_x < FAR_LEFT {
+        return Some((CullReason::FarLeft, !targeted));
+    }
+    
+    // hidden and transparent objects still have hitboxes
+    if gameplay == Some(true) {
+        return None;
+    }
+    let decoration = gameplay == Some(false);
+    if obj.raw(HIDE_KEY) == Some("1") {
+        return Some((CullReason::HiddenFlag, decoration));
+    }
+    let faded = obj.groups().iter().filter_map(|g| alpha.get(g)).copied().reduce(|a, b| {
+        if a == Alpha::Transparent || b == Alpha::Transparent { Alpha::Transparent } else { Alpha::FadedIn }
+    });
+    match faded {
+        Some(Alpha::Transparent) => { return Some((CullReason::Transparent, decoration)); },
+        Some(Alpha::FadedIn) => { return Some((CullReason::FadedIn, false)); },
+        None => (),
+    }
+    if bounds.max_y < BELOW_FLOOR {
+        return Some((CullReason::BelowFloor, false));
+    }
+    None
+}
+
+// removes objects 1.9 would spend time on but nobody would ever see
+pub fn cull_objects(objects: &mut ObjectList, level: CullLevel) -> CullReport {
+    let mut report = CullReport::default();
+    let index = GroupIndex::new(objects);
+    let alpha = alpha_groups(objects, &index);
+    
+    let mut remove = Vec::with_capacity(objects.objects().len());
+    for obj in objects.objects() {
+        let Some((reason, certain)) = cull_reason(obj, &index, &alpha) else {
+            remove.push(false);
+            continue;
+        };
+        if !certain {
+            report.uncertain.push(CullCandidate {
+                id: obj.id(),
+                x_pos: obj.x_pos(),
+                y_pos: obj.y_pos(),
+                reason,
+            });
+        }
+        remove.push(certain || level == CullLevel::Aggressive);
+    }
+    
+    let before = objects.objects().len();
+    let mut remove = remove.into_iter();
+    objects.objects_mut().retain(|_| !remove.next().unwrap());
+    report.removed = before - objects.objects().len();
+    report
+}
+
+// for gameplay only conversions, anything the table says is decoration goes,
+// unknown ids stay since they might be gameplay
+pub fn remove_decoration(objects: &mut ObjectList) -> usize {
+    let before = objects.len();
+    objects.retain(|obj| !ObjectVariant::from_id(obj.id()).is_some_and(|v| v.kind() == ObjectKind::Decoration));
+    before - objects.len()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnsupportedObject {
+    pub id: u16,
+    pub x_pos: f32,
+}
+
+// 1.9 won't load a level with ids it doesn't know, so whatever the other passes
+// couldn't replace has to go
+pub fn remove_unsupported(objects: &mut ObjectList) -> Vec<UnsupportedObject> {
+    let mut removed = Vec::new();
+    objects.retain(|obj| {
+        let supported = GameVersion::from_object_id(obj.id()) <= GameVersion::V1_9;
+        if !supported {
+            removed.push(UnsupportedObject { id: obj.id(), x_pos: obj.x_pos() });
+        }
+        supported
+    });
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const LEVEL: &str = concat!(
+        "kA2,0;",
+        // kept
+        "1,1,2,300,3,15;",
+        "1,1,2,300,3,15,135,1;",
+        "1,8,2,300,3,-60;",
+        // certain
+        "1,1,2,330,3,15,57,1;",
+        "1,1,2,300,3,-400;",
+        "1,18,2,300,3,15,135,1;",
+        "1,18,2,300,3,15,57,3;",
+        // uncertain
+        "1,1,2,360,3,15,57,2;",
+        "1,500,2,300,3,15,135,1;",
+        "1,500,2,300,3,-60;",
+        "1,8,2,-400,3,15,57,4;",
+        // triggers
+        "1,1049,2,0,3,15,51,1;",
+        "1,1049,2,0,3,15,51,2;",
+        "1,1049,2,345,3,15,51,2,56,1;",
+        "1,1007,2,0,3,15,51,3,35,0;",
+        "1,901,2,0,3,15,51,4,28,600;",
+    );
+    
+    #[test]
+    fn conservative() {
+        let mut objects = ObjectList::from_raw_str(LEVEL).unwrap();
+        let report = cull_objects(&mut objects, CullLevel::Conservative);
+        
+        assert_eq!(report.removed, 4);
+        assert_eq!(objects.objects().len(), 12);
+        let uncertain: Vec<CullReason> = report.uncertain.iter().map(|c| c.reason).collect();
+        assert_eq!(uncertain, vec![
+            CullReason::ShownByTrigger, CullReason::HiddenFlag, CullReason::BelowFloor, CullReason::FarLeft,
+        ]);
+    }
+    
+    #[test]
+    fn aggressive() {
+        let mut objects = ObjectList::from_raw_str(LEVEL).unwrap();
+        let report = cull_objects(&mut objects, CullLevel::Aggressive);
+        
+        assert_eq!(report.removed, 8);
+        assert_eq!(report.uncertain.len(), 4);
+        let ids: Vec<u16> = objects.objects().iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![1, 1, 8, 1049, 1049, 1049, 1007, 901]);
+    }
+    
+    #[test]
+    fn decoration() {
+        let mut objects = ObjectList::from_raw_str("kA2,0;1,1,2,15,3,15;1,18,2,15,3,15;1,500,2,15,3,15;1,1049,2,0,3,15;").unwrap();
+        assert_eq!(remove_decoration(&mut objects), 1);
+        assert_eq!(objects.len(), 3);
+    }
+    
+    #[test]
+    fn unsupported() {
+        let mut objects = ObjectList::from_raw_str("kA2,0;1,1,2,15,3,15;1,1007,2,45,3,15;1,744,2,75,3,15;1,1331,2,105,3,15;").unwrap();
+        let removed = remove_unsupported(&mut objects);
+        assert_eq!(removed, vec![
+            UnsupportedObject { id: 1007, x_pos: 45. },
+            UnsupportedObject { id: 1331, x_pos: 105. },
+        ]);
+        let ids: Vec<u16> = objects.iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![1, 744]);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+use crate::codec::deserialise_kv;
+use crate::convert::zorder;
+use crate::models::level::ObjectList;
+use crate::models::object::{is_trigger, Color, LevelObject, ObjectKind, ObjectVariant};
+use crate::models::spatial::{BoundsKind, SpatialIndex};
+use crate::models::trigger::{Trigger, TriggerKind};
+
+const HIDE_KEY: &str = "135";
+const MAIN_COLOR_KEY: &str = "21";
+// 2.0 keeps every channel in one header key, 1.9 gives each its own
+const CHANNELS_KEY: &str = "kS38";
+const LEGACY_CHANNEL_KEYS: [(&str, u16); 9] = [
+    ("kS29", 1000), ("kS30", 1001), ("kS31", 1002), ("kS32", 1004), ("kS33", 1),
+    ("kS34", 2), ("kS35", 3), ("kS36", 4), ("kS37", 1003),
+];
+// where objects without a colour get drawn
+const OBJECT_CHANNEL: u16 = 1004;
+
+// -0 and 0 should count as the same position
+fn bits(v: f32) -> u32 {
+    (v + 0.).to_bits()
+}
+
+// everything that has to match for two objects to look the same
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct DuplicateKey<'a> {
+    id: u16,
+    rotation: u32,
+    flip: (bool, bool),
+    color: Option<Color>,
+    base_hsv: Option<&'a str>,
+    scale: Option<&'a str>,
+}
+
+impl<'a> DuplicateKey<'a> {
+    fn new(obj: &'a LevelObject) -> DuplicateKey<'a> {
+        DuplicateKey {
+            id: obj.id(),
+            rotation: bits(obj.rotation()),
+            flip: obj.flip(),
+            color: obj.color(),
+            base_hsv: obj.base_hsv(),
+            scale: obj.raw("32"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DedupReport {
+    pub removed: usize,
+}
+
+// removes exact copies stacked on top of each other, keeping the first of each
+pub fn remove_duplicates(objects: &mut ObjectList) -> DedupReport {
+    // bucketed by exact position, then compared on everything else
+    let mut buckets: HashMap<(u32, u32), Vec<DuplicateKey>> = HashMap::new();
+    let mut keep = Vec::with_capacity(objects.objects().len());
+    for obj in objects.objects() {
+        // triggers stacked on purpose still do something each
+        if is_trigger(obj.id()) {
+            keep.push(true);
+            continue;
+        }
+        let bucket = buckets.entry((bits(obj.x_pos()), bits(obj.y_pos()))).or_default();
+        let key = DuplicateKey::new(obj);
+        if bucket.contains(&key) {
+            keep.push(false);
+        } else {
+            bucket.push(key);
+            keep.push(true);
+        }
+    }
+    drop(buckets);
+    
+    let before = objects.objects().len();
+    let mut keep = keep.into_iter();
+    objects.objects_mut().retain(|_| keep.next().unwrap());
+    DedupReport { removed: before - objects.objects().len() }
+}
+
+// z layer, then z order, then list order, since later objects draw on top of earlier ones
+fn draw_order(obj: &LevelObject, variant: Option<&ObjectVariant>, index: usize) -> (i8, i32, usize) {
+    let (layer, z_order) = zorder::draw_order(obj, variant);
+    (layer, z_order, index)
+}
+
+fn channel(obj: &LevelObject) -> u16 {
+    obj.raw(MAIN_COLOR_KEY)
+        .and_then(|c| c.parse().ok())
+        .filter(|&c| c != 0)
+        .or_else(|| obj.color().map(Color::new_id))
+        .unwrap_or(OBJECT_CHANNEL)
+}
+
+// channels that might not cover what's under them, blended or see-through in the header
+// or changed by a colour trigger at some point
+fn see_through_channels(objects: &ObjectList) -> HashSet<u16> {
+    let mut channels = HashSet::new();
+    let header = objects.header_value(CHANNELS_KEY).into_iter()
+        .flat_map(|v| v.split('|'))
+        .map(|channel| deserialise_kv(channel, "_"))
+        .filter_map(|map| Some((map.get("6")?.parse().ok()?, map)));
+    let legacy = LEGACY_CHANNEL_KEYS.iter()
+        .filter_map(|(key, id)| Some((*id, deserialise_kv(objects.header_value(key)?, "_"))));
+    for (id, map) in header.chain(legacy) {
+        let blending = map.get("5").is_some_and(|v| v == "1");
+        let opacity: f32 = map.get("7").and_then(|v| v.parse().ok()).unwrap_or(1.);
+        if blending || opacity < 1. {
+            channels.insert(id);
+        }
+    }
+    for obj in objects.iter() {
+        if let Some(Trigger { kind: TriggerKind::Color(color), .. }) = Trigger::from_object(obj) {
+            if color.blending || color.opacity < 1. || color.copy_channel.is_some() {
+                channels.insert(color.channel);
+            }
+        }
+    }
+    channels
+}
+
+// an opaque block that can't move, fade or hide, rotated so it still lines up with the grid.
+// without groups no alpha, toggle or move trigger can reach it
+fn is_occluder(obj: &LevelObject, variant: Option<&ObjectVariant>, see_through: &HashSet<u16>) -> bool {
+    variant.is_some_and(|v| v.is_opaque())
+        && obj.groups().is_empty()
+        && obj.raw(HIDE_KEY) != Some("1")
+        && obj.rotation().rem_euclid(90.) == 0.
+        && !see_through.contains(&channel(obj))
+}
+
+#[derive(Debug, Default)]
+pub struct OcclusionReport {
+    pub removed: usize,
+}
+
+// removes decoration completely covered by an opaque block drawn on top of it
+pub fn remove_occluded(objects: &mut ObjectList) -> OcclusionReport {
+    let objs = objects.objects();
+    let variants: Vec<Option<ObjectVariant>> = objs.iter().map(|obj| ObjectVariant::from_id(obj.id())).collect();
+    let see_through = see_through_channels(objects);
+    
+    let index = SpatialIndex::new(objects, BoundsKind::Sprite);
+    
+    let mut keep = Vec::with_capacity(objs.len());
+    for (i, obj) in objs.iter().enumerate() {
+        let variant = variants[i].as_ref();
+        // hitboxes count even when nobody can see them, and unknown ids might have one
+        if variant.map(|v| v.kind()) != Some(ObjectKind::Decoration) {
+            keep.push(true);
+            continue;
+        }
+        let rect = index.bounds(i).unwrap();
+        let order = draw_order(obj, variant, i);
+        let occluded = index.query(&rect).into_iter().any(|j| {
+            let occluder = &objs[j];
+            let occluder_variant = variants[j].as_ref();
+            j != i
+                && is_occluder(occluder, occluder_variant, &see_through)
+                && draw_order(occluder, occluder_variant, j) > order
+                && index.bounds(j).is_some_and(|b| b.contains(&rect))
+        });
+        keep.push(!occluded);
+    }
+    
+    let before = objs.len();
+    let mut keep = keep.into_iter();
+    objects.objects_mut().retain(|_| keep.next().unwrap());
+    OcclusionReport { removed: before - objects.objects().len() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn duplicates() {
+        let raw = concat!(
+            "kA2,0;",
+            "1,1,2,15,3,15;",
+            "1,1,2,15,3,15;",
+            "1,1,2,15,3,15,6,90;",
+            "1,1,2,15,3,15,22,3;",
+            "1,1,2,15,3,15,22,3;",
+            "1,1,2,45,3,15;",
+            "1,901,2,15,3,15;",
+            "1,901,2,15,3,15;",
+        );
+        let mut objects = ObjectList::from_raw_str(raw).unwrap();
+        let report = remove_duplicates(&mut objects);
+        assert_eq!(report.removed, 2);
+        assert_eq!(objects.objects().len(), 6);
+        
+        // running it again gives the same thing
+        let mut again = ObjectList::from_raw_str(raw).unwrap();
+        remove_duplicates(&mut again);
+        assert_eq!(objects.string().unwrap(), again.string().unwrap());
+    }
+    
+    #[test]
+    fn occluded() {
+        let mut objects = ObjectList::from_raw_str(concat!(
+            "kA2,0,kS38,6_5_5_1|6_6_7_0.5|;",
+            // covered by the block after it
+            "1,18,2,15,3,15;",
+            "1,1,2,15,3,15;",
+            // drawn on top of the block
+            "1,18,2,15,3,15,25,5;",
+            // sticks out of the block
+            "1,18,2,20,3,15;",
+            // behind a slab, but only half covered
+            "1,18,2,75,3,15,25,-1;",
+            "1,40,2,75,3,7.5;",
+            // behind a slab, fully covered
+            "1,18,2,105,3,7.5,25,-1,32,0.5;",
+            "1,40,2,105,3,7.5;",
+            // the block is in a group, so it might move
+            "1,18,2,135,3,15,25,-1;",
+            "1,1,2,135,3,15,57,2;",
+            // spikes and orbs keep their hitboxes even when covered
+            "1,36,2,165,3,15,25,-1,32,0.5;",
+            "1,39,2,165,3,7.5,25,-1;",
+            "1,1,2,165,3,15;",
+            // blended and half see-through channels, and one a colour trigger fades
+            "1,18,2,195,3,15,25,-1;",
+            "1,1,2,195,3,15,21,5;",
+            "1,18,2,225,3,15,25,-1;",
+            "1,1,2,225,3,15,21,6;",
+            "1,18,2,255,3,15,25,-1;",
+            "1,1,2,255,3,15,21,7;",
+            "1,899,2,0,3,0,23,7,35,0.2;",
+            // no z layer means T1, which draws over a block on B1
+            "1,18,2,285,3,15;",
+            "1,1,2,285,3,15,24,3;",
+        )).unwrap();
+        let report = remove_occluded(&mut objects);
+        
+        assert_eq!(report.removed, 2);
+        let xs: Vec<(u16, f32)> = objects.objects().iter().map(|obj| (obj.id(), obj.x_pos())).collect();
+        assert_eq!(xs, vec![
+            (1, 15.), (18, 15.), (18, 20.), (18, 75.), (40, 75.), (40, 105.),
+            (18, 135.), (1, 135.), (36, 165.), (39, 165.), (1, 165.),
+            (18, 195.), (1, 195.), (18, 225.), (1, 225.), (18, 255.), (1, 255.), (899, 0.),
+            (18, 285.), (1, 285.),
+        ]);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::models::level::ObjectList;
+
+const START_POS_ID: u16 = 31;
+// gamemode key for both the level header and start positions
+const GAMEMODE_KEY: &str = "kA2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gamemode {
+    Cube,
+    Ship,
+    Ball,
+    Ufo,
+    Wave,
+    Robot,
+    Spider,
+    Swing,
+}
+impl Gamemode {
+    pub fn from_portal_id(id: u16) -> Option<Gamemode> {
+        match id {
+            12 => Some(Gamemode::Cube),
+            13 => Some(Gamemode::Ship),
+            47 => Some(Gamemode::Ball),
+            111 => Some(Gamemode::Ufo),
+            660 => Some(Gamemode::Wave),
+            745 => Some(Gamemode::Robot),
+            1331 => Some(Gamemode::Spider),
+            1933 => Some(Gamemode::Swing),
+            _ => None,
+        }
+    }
+    pub fn portal_id(self) -> u16 {
+        match self {
+            Gamemode::Cube => 12,
+            Gamemode::Ship => 13,
+            Gamemode::Ball => 47,
+            Gamemode::Ufo => 111,
+            Gamemode::Wave => 660,
+            Gamemode::Robot => 745,
+            Gamemode::Spider => 1331,
+            Gamemode::Swing => 1933,
+        }
+    }
+    // value used by kA2 in the header and start positions
+    pub fn from_setting(value: u8) -> Option<Gamemode> {
+        match value {
+            0 => Some(Gamemode::Cube),
+            1 => Some(Gamemode::Ship),
+            2 => Some(Gamemode::Ball),
+            3 => Some(Gamemode::Ufo),
+            4 => Some(Gamemode::Wave),
+            5 => Some(Gamemode::Robot),
+            6 => Some(Gamemode::Spider),
+            7 => Some(Gamemode::Swing),
+            _ => None,
+        }
+    }
+    pub fn setting(self) -> u8 {
+        match self {
+            Gamemode::Cube => 0,
+            Gamemode::Ship => 1,
+            Gamemode::Ball => 2,
+            Gamemode::Ufo => 3,
+            Gamemode::Wave => 4,
+            Gamemode::Robot => 5,
+            Gamemode::Spider => 6,
+            Gamemode::Swing => 7,
+        }
+    }
+    pub fn in_19(self) -> bool {
+        matches!(self, Gamemode::Cube | Gamemode::Ship | Gamemode::Ball | Gamemode::Ufo | Gamemode::Wave)
+    }
+}
+
+// which 1.9 gamemode each unsupported gamemode gets turned into
+#[derive(Debug, Clone, Copy)]
+pub struct GamemodePolicy {
+    pub robot: Gamemode,
+    pub spider: Gamemode,
+    pub swing: Gamemode,
+}
+impl Default for GamemodePolicy {
+    fn default() -> Self {
+        GamemodePolicy {
+            robot: Gamemode::Cube,
+            spider: Gamemode::Ball,
+            swing: Gamemode::Ship,
+        }
+    }
+}
+impl GamemodePolicy {
+    pub fn replacement(&self, mode: Gamemode) -> Gamemode {
+        let replacement = match mode {
+            Gamemode::Robot => self.robot,
+            Gamemode::Spider => self.spider,
+            Gamemode::Swing => self.swing,
+            _ => mode,
+        };
+        // a policy pointing at another unsupported mode falls back to cube
+        if replacement.in_19() { replacement } else { Gamemode::Cube }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamemodeSource {
+    Header,
+    StartPos,
+    Portal,
+}
+
+// x_end is the next gamemode portal, or the last object if there isn't one
+#[derive(Debug, Clone)]
+pub struct GamemodeChange {
+    pub source: GamemodeSource,
+    pub from: Gamemode,
+    pub to: Gamemode,
+    pub x_start: f32,
+    pub x_end: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct GamemodeReport {
+    pub changes: Vec<GamemodeChange>,
+}
+
+pub fn downgrade_gamemodes(objects: &mut ObjectList, policy: &GamemodePolicy) -> GamemodeReport {
+    let mut report = GamemodeReport::default();
+    
+    // portal positions are needed for the ranges, so gather them before replacing anything
+    let mut portal_xs: Vec<f32> = objects.objects().iter()
+        .filter(|obj| Gamemode::from_portal_id(obj.id()).is_some())
+        .map(|obj| obj.x_pos())
+        .collect();
+    portal_xs.sort_by(f32::total_cmp);
+    let level_end = objects.objects().iter()
+        .map(|obj| obj.x_pos())
+        .fold(0., f32::max);
+    let range_end = |x: f32| {
+        portal_xs.iter().copied().find(|&p| p > x).unwrap_or(level_end)
+    };
+    
+    let header_mode = objects.header_value(GAMEMODE_KEY)
+        .and_then(|v| v.parse().ok())
+        .and_then(Gamemode::from_setting);
+    if let Some(mode) = header_mode.filter(|m| !m.in_19()) {
+        let to = policy.replacement(mode);
+        objects.set_header_value(GAMEMODE_KEY, to.setting().to_string());
+        report.changes.push(GamemodeChange {
+            source: GamemodeSource::Header,
+            from: mode,
+            to,
+            x_start: 0.,
+            x_end: range_end(0.),
+        });
+    }
+    
+    for obj in objects.objects_mut().iter_mut() {
+        let (source, mode) = if obj.id() == START_POS_ID {
+            match obj.raw(GAMEMODE_KEY).and_then(|v| v.parse().ok()).and_then(Gamemode::from_setting) {
+                Some(mode) => (GamemodeSource::StartPos, mode),
+                None => continue,
+            }
+        } else {
+            match Gamemode::from_portal_id(obj.id()) {
+                Some(mode) => (GamemodeSource::Portal, mode),
+                None => continue,
+            }
+        };
+        if mode.in_19() {
+            continue;
+        }
+        
+        let to = policy.replacement(mode);
+        match source {
+            GamemodeSource::StartPos => obj.set_other(GAMEMODE_KEY, to.setting().to_string()),
+            _ => obj.set_id(to.portal_id()),
+        }
+        report.changes.push(GamemodeChange {
+            source,
+            from: mode,
+            to,
+            x_start: obj.x_pos(),
+            x_end: range_end(obj.x_pos()),
+        });
+    }
+    
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn object_list(raw: &str) -> ObjectList {
+        ObjectList::from_raw_str(raw).unwrap()
+    }
+    
+    #[test]
+    fn replaces_portals() {
+        let mut objects = object_list("kA2,0;1,745,2,100,3,15;1,13,2,300,3,15;1,1933,2,500,3,15;1,1,2,900,3,15;");
+        let report = downgrade_gamemodes(&mut objects, &GamemodePolicy::default());
+        
+        let ids: Vec<u16> = objects.objects().iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![12, 13, 13, 1]);
+        
+        assert_eq!(report.changes.len(), 2);
+        let robot = &report.changes[0];
+        assert_eq!(robot.from, Gamemode::Robot);
+        assert_eq!(robot.to, Gamemode::Cube);
+        assert_eq!((robot.x_start, robot.x_end), (100., 300.));
+        let swing = &report.changes[1];
+        assert_eq!(swing.to, Gamemode::Ship);
+        // no portal after it, so it runs until the last object
+        assert_eq!((swing.x_start, swing.x_end), (500., 900.));
+    }
+    
+    #[test]
+    fn replaces_header_and_start_pos() {
+        let mut objects = object_list("kA2,6;1,31,2,50,3,15,kA2,5;1,12,2,200,3,15;");
+        let policy = GamemodePolicy { spider: Gamemode::Wave, ..Default::default() };
+        let report = downgrade_gamemodes(&mut objects, &policy);
+        
+        assert_eq!(objects.header_value("kA2"), Some("4"));
+        assert_eq!(objects.objects()[0].raw("kA2"), Some("0"));
+        
+        assert_eq!(report.changes.len(), 2);
+        assert_eq!(report.changes[0].source, GamemodeSource::Header);
+        assert_eq!((report.changes[0].x_start, report.changes[0].x_end), (0., 200.));
+        assert_eq!(report.changes[1].source, GamemodeSource::StartPos);
+        assert_eq!((report.changes[1].x_start, report.changes[1].x_end), (50., 200.));
+    }
+    
+    #[test]
+    fn unsupported_policy_falls_back() {
+        let policy = GamemodePolicy { robot: Gamemode::Spider, ..Default::default() };
+        assert_eq!(policy.replacement(Gamemode::Robot), Gamemode::Cube);
+        assert_eq!(policy.replacement(Gamemode::Ufo), Gamemode::Ufo);
+    }
+}
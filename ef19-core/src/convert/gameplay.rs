@@ -0,0 +1,84 @@
+use crate::models::level::ObjectList;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameplayAction {
+    Substituted(u16),
+    Removed,
+}
+
+// closest 1.9 behaviour for gameplay objects added after 1.9
+pub fn gameplay_replacement(id: u16) -> Option<GameplayAction> {
+    match id {
+        // green orb flips gravity and jumps, blue orb only flips
+        1022 => Some(GameplayAction::Substituted(84)),
+        // red pad/orb are stronger yellow ones
+        1332 => Some(GameplayAction::Substituted(35)),
+        1333 => Some(GameplayAction::Substituted(36)),
+        // spider pad/orb teleport to the other side, blue ones at least flip gravity
+        3005 => Some(GameplayAction::Substituted(67)),
+        3004 => Some(GameplayAction::Substituted(84)),
+        // black orb, dash orbs, toggle orb, teleport orb, teleport portals
+        1330 | 1704 | 1751 | 1594 | 3027 | 747 | 749 => Some(GameplayAction::Removed),
+        _ => None,
+    }
+}
+
+// every change here touches gameplay, so each one is a possible playability break
+#[derive(Debug, Clone)]
+pub struct GameplayChange {
+    pub id: u16,
+    pub action: GameplayAction,
+    pub x_pos: f32,
+    pub y_pos: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct GameplayReport {
+    pub changes: Vec<GameplayChange>,
+}
+
+pub fn substitute_gameplay_objects(objects: &mut ObjectList) -> GameplayReport {
+    let mut report = GameplayReport::default();
+    
+    objects.objects_mut().retain_mut(|obj| {
+        let Some(action) = gameplay_replacement(obj.id()) else { return true; };
+        report.changes.push(GameplayChange {
+            id: obj.id(),
+            action,
+            x_pos: obj.x_pos(),
+            y_pos: obj.y_pos(),
+        });
+        match action {
+            GameplayAction::Substituted(id) => {
+                obj.set_id(id);
+                true
+            },
+            GameplayAction::Removed => false,
+        }
+    });
+    
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn substitutes_and_removes() {
+        let mut objects = ObjectList::from_raw_str(
+            "kA2,0;1,1022,2,45,3,75;1,36,2,75,3,75;1,1704,2,105,3,45;1,747,2,135,3,15,54,90;1,3005,2,165,3,15;"
+        ).unwrap();
+        let report = substitute_gameplay_objects(&mut objects);
+        
+        let ids: Vec<u16> = objects.objects().iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![84, 36, 67]);
+        
+        assert_eq!(report.changes.len(), 4);
+        assert_eq!(report.changes[0].action, GameplayAction::Substituted(84));
+        assert_eq!(report.changes[1].id, 1704);
+        assert_eq!(report.changes[1].action, GameplayAction::Removed);
+        assert_eq!(report.changes[2].x_pos, 135.);
+        assert_eq!(report.changes[3].action, GameplayAction::Substituted(67));
+    }
+}
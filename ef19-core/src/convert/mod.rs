@@ -0,0 +1,11 @@
+pub mod gamemode;
+pub mod gameplay;
+pub mod retime;
+pub mod bake;
+pub mod cull;
+pub mod dedup;
+pub mod validate;
+pub mod colors;
+pub mod zorder;
+pub mod report;
+pub mod pipeline;
@@ -0,0 +1,501 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use crate::convert::bake::bake_move_triggers;
+use crate::convert::colors::downgrade_colors;
+use crate::convert::cull::{cull_objects, remove_decoration, remove_unsupported, CullLevel};
+use crate::convert::dedup::{remove_duplicates, remove_occluded};
+use crate::convert::gamemode::{downgrade_gamemodes, GamemodePolicy, GamemodeSource};
+use crate::convert::gameplay::{substitute_gameplay_objects, GameplayAction};
+use crate::convert::report::{id_counts, ConversionReport, Severity, TriggerOutcome};
+use crate::convert::retime::retime_fastest;
+use crate::convert::validate::{validate_for_19, ValidationReport};
+use crate::convert::zorder::flatten_z_order;
+use crate::models::level::Level;
+use crate::models::object::{is_trigger, Color, ObjectKind, ObjectVariant};
+use crate::models::timeline::Speed;
+use crate::models::trigger::{COLOR_TRIGGER_ID, MOVE_TRIGGER_ID};
+use crate::errors::EResult;
+
+// one step of a conversion, each pass keeps its own settings
+pub trait Pass {
+    fn name(&self) -> &'static str;
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()>;
+    
+    // passes that only turn 2.x content into something 1.9 has are skipped
+    // for levels 1.9 can already open, the rest are the preset's choice
+    fn converts(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ColorPass;
+
+impl Pass for ColorPass {
+    fn name(&self) -> &'static str {
+        "colors"
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let colors = downgrade_colors(level.object_list()?);
+        for converted in colors.converted {
+            report.trigger(self.name(), COLOR_TRIGGER_ID, converted.x_pos, TriggerOutcome::Converted { to: converted.legacy_id });
+        }
+        for lost in colors.lost {
+            report.trigger(self.name(), COLOR_TRIGGER_ID, lost.x_pos, TriggerOutcome::Lost);
+            report.remove(COLOR_TRIGGER_ID, 1);
+            let message = format!("colour trigger for channel {} removed", lost.channel);
+            report.warn(self.name(), Severity::Visual, message, Some(lost.x_pos));
+        }
+        if colors.lost_settings > 0 {
+            let message = format!("{} colour triggers lost copy colour or opacity", colors.lost_settings);
+            report.warn(self.name(), Severity::Visual, message, None);
+        }
+        for &channel in colors.remapped.keys() {
+            report.color_channels.insert(channel, Color::from_new_id(channel));
+        }
+        for (channel, count) in colors.unmapped {
+            report.color_channels.insert(channel, None);
+            let message = format!("{count} objects on channel {channel} left on their default colour");
+            report.warn(self.name(), Severity::Visual, message, None);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GamemodePass {
+    pub policy: GamemodePolicy,
+}
+
+impl Pass for GamemodePass {
+    fn name(&self) -> &'static str {
+        "gamemodes"
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let gamemodes = downgrade_gamemodes(level.object_list()?, &self.policy);
+        for change in gamemodes.changes {
+            // start positions only get their setting changed
+            if change.source == GamemodeSource::Portal {
+                report.substitute(change.from.portal_id(), change.to.portal_id());
+            }
+            let message = format!("{:?} replaced with {:?}", change.from, change.to);
+            report.warn_range(self.name(), Severity::Gameplay, message, change.x_start, change.x_end);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GameplayPass;
+
+impl Pass for GameplayPass {
+    fn name(&self) -> &'static str {
+        "gameplay"
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let gameplay = substitute_gameplay_objects(level.object_list()?);
+        for change in gameplay.changes {
+            let message = match change.action {
+                GameplayAction::Substituted(to) => {
+                    report.substitute(change.id, to);
+                    format!("object {} replaced with {to}", change.id)
+                },
+                GameplayAction::Removed => {
+                    report.remove(change.id, 1);
+                    format!("object {} removed", change.id)
+                },
+            };
+            report.warn(self.name(), Severity::Gameplay, message, Some(change.x_pos));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RetimePass;
+
+impl Pass for RetimePass {
+    fn name(&self) -> &'static str {
+        "retime"
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let retime = retime_fastest(level.object_list()?);
+        for _ in 0..retime.portals {
+            report.substitute(Speed::Fastest.portal_id(), Speed::Faster.portal_id());
+        }
+        for (x_start, _) in retime.sections {
+            report.warn(self.name(), Severity::Gameplay, "4x speed section stretched to 3x".to_string(), Some(x_start));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BakePass;
+
+impl Pass for BakePass {
+    fn name(&self) -> &'static str {
+        "bake"
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let bake = bake_move_triggers(level.object_list()?);
+        report.remove(MOVE_TRIGGER_ID, bake.baked.len());
+        for baked in bake.baked {
+            report.trigger(self.name(), MOVE_TRIGGER_ID, baked.x_pos, TriggerOutcome::Baked);
+        }
+        // the unsupported pass records these as lost when it strips them
+        for skipped in bake.skipped {
+            let message = format!("move trigger for group {} not baked ({:?})", skipped.group, skipped.reason);
+            report.warn(self.name(), Severity::Gameplay, message, Some(skipped.x_pos));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ZOrderPass;
+
+impl Pass for ZOrderPass {
+    fn name(&self) -> &'static str {
+        "z-order"
+    }
+    
+    fn run(&self, level: &mut Level, _report: &mut ConversionReport) -> EResult<()> {
+        flatten_z_order(level.object_list()?);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DedupPass {
+    // also remove objects hidden behind opaque blocks
+    pub occlusion: bool,
+}
+
+impl Pass for DedupPass {
+    fn name(&self) -> &'static str {
+        "dedup"
+    }
+    
+    fn converts(&self) -> bool {
+        false
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let objects = level.object_list()?;
+        let before = id_counts(objects);
+        remove_duplicates(objects);
+        if self.occlusion {
+            remove_occluded(objects);
+        }
+        report.remove_since(&before, objects);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CullPass {
+    pub level: CullLevel,
+}
+
+impl Pass for CullPass {
+    fn name(&self) -> &'static str {
+        "cull"
+    }
+    
+    fn converts(&self) -> bool {
+        false
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let objects = level.object_list()?;
+        let before = id_counts(objects);
+        let cull = cull_objects(objects, self.level);
+        report.remove_since(&before, objects);
+        // only worth mentioning when they were kept
+        if self.level == CullLevel::Conservative {
+            for candidate in cull.uncertain {
+                let message = format!("object {} might never be seen ({:?})", candidate.id, candidate.reason);
+                report.warn(self.name(), Severity::Info, message, Some(candidate.x_pos));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DecorationPass;
+
+impl Pass for DecorationPass {
+    fn name(&self) -> &'static str {
+        "decoration"
+    }
+    
+    fn converts(&self) -> bool {
+        false
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let objects = level.object_list()?;
+        let before = id_counts(objects);
+        remove_decoration(objects);
+        report.remove_since(&before, objects);
+        Ok(())
+    }
+}
+
+// goes last so the other passes get to replace what they can first
+#[derive(Debug, Default)]
+pub struct UnsupportedPass;
+
+impl Pass for UnsupportedPass {
+    fn name(&self) -> &'static str {
+        "unsupported"
+    }
+    
+    fn run(&self, level: &mut Level, report: &mut ConversionReport) -> EResult<()> {
+        let mut by_id: BTreeMap<u16, (usize, f32)> = BTreeMap::new();
+        for removed in remove_unsupported(level.object_list()?) {
+            if is_trigger(removed.id) {
+                report.trigger(self.name(), removed.id, removed.x_pos, TriggerOutcome::Lost);
+            }
+            let (count, _) = by_id.entry(removed.id).or_insert((0, removed.x_pos));
+            *count += 1;
+        }
+        for (id, (count, x_pos)) in by_id {
+            report.remove(id, count);
+            let decoration = ObjectVariant::from_id(id).is_some_and(|v| v.kind() == ObjectKind::Decoration);
+            let severity = if decoration && !is_trigger(id) { Severity::Visual } else { Severity::Gameplay };
+            let message = format!("removed {count} of object {id}, 1.9 doesn't have it");
+            report.warn(self.name(), severity, message, Some(x_pos));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    // keeps everything that can be kept
+    #[default]
+    Faithful,
+    // also throws out whatever probably won't be missed, for weaker devices
+    Lightweight,
+    // only what the player interacts with
+    GameplayOnly,
+}
+
+impl FromStr for Preset {
+    type Err = String;
+    
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "faithful" => Ok(Preset::Faithful),
+            "lightweight" => Ok(Preset::Lightweight),
+            "gameplay-only" => Ok(Preset::GameplayOnly),
+            _ => Err(format!("unknown preset {name}, expected faithful, lightweight or gameplay-only")),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Preset::Faithful => write!(f, "faithful"),
+            Preset::Lightweight => write!(f, "lightweight"),
+            Preset::GameplayOnly => write!(f, "gameplay-only"),
+        }
+    }
+}
+
+// passes run in the order they were added
+#[derive(Default)]
+pub struct Pipeline {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+    
+    pub fn with<P: Pass + 'static>(mut self, pass: P) -> Pipeline {
+        self.passes.push(Box::new(pass));
+        self
+    }
+    
+    // moves get baked before retiming so they're stretched with everything else,
+    // and anything 1.9 still can't load is stripped at the very end
+    pub fn preset(preset: Preset) -> Pipeline {
+        let pipeline = Pipeline::new()
+            .with(GameplayPass)
+            .with(GamemodePass::default())
+            .with(BakePass)
+            .with(RetimePass);
+        let pipeline = match preset {
+            Preset::Faithful => pipeline
+                .with(ColorPass)
+                .with(ZOrderPass)
+                .with(DedupPass { occlusion: false })
+                .with(CullPass { level: CullLevel::Conservative }),
+            Preset::Lightweight => pipeline
+                .with(ColorPass)
+                .with(ZOrderPass)
+                .with(DedupPass { occlusion: true })
+                .with(CullPass { level: CullLevel::Aggressive }),
+            Preset::GameplayOnly => pipeline
+                .with(DecorationPass)
+                .with(DedupPass { occlusion: false })
+                .with(CullPass { level: CullLevel::Aggressive }),
+        };
+        pipeline.with(UnsupportedPass)
+    }
+    
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+    
+    // the level's metadata is refreshed afterwards so it matches the new objects
+    pub fn run(&self, level: &mut Level) -> EResult<ConversionReport> {
+        let mut report = ConversionReport::default();
+        let needs_conversion = level.needs_conversion()?;
+        for pass in self.passes.iter().filter(|pass| needs_conversion || !pass.converts()) {
+            pass.run(level, &mut report)?;
+            report.passes.push(pass.name());
+        }
+        level.recompute_metadata()?;
+        Ok(report)
+    }
+    
+    // runs then checks the result against 1.9, what the cli and web api both do
+    pub fn run_validated(&self, level: &mut Level) -> EResult<(ConversionReport, ValidationReport)> {
+        let report = self.run(level)?;
+        let settings = level.settings();
+        let validation = validate_for_19(level.object_list()?, &settings);
+        Ok((report, validation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::codec;
+    use crate::convert::report::Substitution;
+    use crate::models::level::{Password, Song};
+    
+    fn level(raw: &str) -> Level {
+        Level {
+            name: "awawa".to_string(),
+            description: String::new(),
+            object_str: codec::zip_string(raw).unwrap(),
+            object_list: None,
+            song: Song::Official(0),
+            password: Password::default(),
+            version: 1,
+            game_version: 22,
+            binary_version: 0,
+            length: 0,
+            is_two_player: false,
+            object_count: 0,
+            has_low_detail: false,
+        }
+    }
+    
+    const RAW: &str = concat!(
+        "kA2,0,kA4,0;",
+        "1,1,2,15,3,15;",
+        "1,1,2,15,3,15;",
+        "1,18,2,45,3,15;",
+        "1,1331,2,75,3,15;",
+        "1,899,2,0,3,0,23,60;",
+    );
+    
+    #[test]
+    fn presets() {
+        let mut faithful = level(RAW);
+        let report = Pipeline::preset(Preset::Faithful).run(&mut faithful).unwrap();
+        assert_eq!(report.passes, vec!["gameplay", "gamemodes", "bake", "retime", "colors", "z-order", "dedup", "cull", "unsupported"]);
+        let ids: Vec<u16> = faithful.object_list().unwrap().iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![1, 18, 47]);
+        let warned: Vec<&str> = report.warnings.iter().map(|w| w.pass).collect();
+        assert_eq!(warned, vec!["gamemodes", "colors"]);
+        assert_eq!(report.removed, BTreeMap::from([(1, 1), (899, 1)]));
+        assert_eq!(report.substituted, vec![Substitution { from: 1331, to: 47, count: 1 }]);
+        assert_eq!(report.triggers.len(), 1);
+        assert_eq!(report.worst_severity(), Some(Severity::Gameplay));
+        
+        let mut gameplay = level(RAW);
+        Pipeline::preset(Preset::GameplayOnly).run(&mut gameplay).unwrap();
+        // the colour trigger isn't converted here, so it's stripped
+        assert_eq!(gameplay.object_list().unwrap().len(), 2);
+        assert_eq!(gameplay.object_count, 2);
+        
+        // already a 1.9 level, only the clean up passes run
+        let mut saved_by_19 = level(RAW);
+        saved_by_19.game_version = 19;
+        let report = Pipeline::preset(Preset::Faithful).run(&mut saved_by_19).unwrap();
+        assert_eq!(report.passes, vec!["dedup", "cull"]);
+        let ids: Vec<u16> = saved_by_19.object_list().unwrap().iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![1, 18, 1331, 899]);
+    }
+    
+    #[test]
+    fn reports_baked_and_retimed_objects() {
+        let mut level = level(concat!(
+            "kA2,0;",
+            "1,1,2,300,3,15,57,2;",
+            "1,901,2,100,3,15,51,2,28,10;",
+            "1,1334,2,400,3,15;",
+            "1,1,2,700,3,15;",
+        ));
+        let (report, validation) = Pipeline::preset(Preset::Faithful).run_validated(&mut level).unwrap();
+        assert_eq!(report.removed, BTreeMap::from([(901, 1)]));
+        assert_eq!(report.substituted, vec![Substitution { from: 1334, to: 203, count: 1 }]);
+        
+        let json: serde_json::Value = serde_json::from_str(&report.to_json_with(&validation).unwrap()).unwrap();
+        assert_eq!(json["removed"]["901"], 1);
+        assert!(json["validation"]["issues"].is_array());
+    }
+    
+    #[test]
+    fn strips_what_cant_be_converted() {
+        let mut level = level(concat!(
+            "kA2,0;",
+            "1,1,2,300,3,15,57,2.3;",
+            // alpha triggers can't be baked
+            "1,1007,2,100,3,15,51,2,35,0.5;",
+            // touch triggered, so the move isn't baked either
+            "1,901,2,100,3,15,51,3,28,10,11,1;",
+            "1,1049,2,150,3,15,51,4;",
+        ));
+        let (report, validation) = Pipeline::preset(Preset::Faithful).run_validated(&mut level).unwrap();
+        validation.ensure_no_crashes().unwrap();
+        let ids: Vec<u16> = level.object_list().unwrap().iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![1]);
+        assert_eq!(report.removed, BTreeMap::from([(901, 1), (1007, 1), (1049, 1)]));
+        let stripped = report.warnings.iter().filter(|w| w.pass == "unsupported").count();
+        assert_eq!(stripped, 3);
+        let lost: Vec<(u16, f32)> = report.triggers.iter()
+            .filter(|t| t.outcome == TriggerOutcome::Lost)
+            .map(|t| (t.id, t.x_pos))
+            .collect();
+        assert_eq!(lost, vec![(1007, 100.), (901, 100.), (1049, 150.)]);
+    }
+    
+    #[test]
+    fn custom_pipeline() {
+        let mut level = level(RAW);
+        let pipeline = Pipeline::new().with(DedupPass::default());
+        assert_eq!(pipeline.pass_names(), vec!["dedup"]);
+        pipeline.run(&mut level).unwrap();
+        assert_eq!(level.object_list().unwrap().len(), 4);
+        assert_eq!("gameplay-only".parse::<Preset>(), Ok(Preset::GameplayOnly));
+        assert!("awawa".parse::<Preset>().is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::Serialize;
+use crate::convert::validate::ValidationReport;
+use crate::models::level::ObjectList;
+use crate::models::object::Color;
+use crate::errors::EResult;
+
+// shared by conversion warnings and validation issues, worst last
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    // nothing a player would notice
+    Info,
+    // the level looks different
+    Visual,
+    // the level plays differently, might not be beatable anymore
+    Gameplay,
+    // 1.9 won't load it, or the level can't be opened
+    Crash,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    // name of the pass that raised it
+    pub pass: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    // None for warnings about the whole level
+    pub x_pos: Option<f32>,
+    // where it stops applying, for warnings that cover a stretch of the level
+    pub x_end: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Substitution {
+    pub from: u16,
+    pub to: u16,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "lowercase")]
+pub enum TriggerOutcome {
+    // rewritten as a trigger 1.9 has
+    Converted { to: u16 },
+    // applied to the objects directly and removed
+    Baked,
+    // 1.9 won't do what it did
+    Lost,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggerChange {
+    pub pass: &'static str,
+    pub id: u16,
+    pub x_pos: f32,
+    #[serde(flatten)]
+    pub outcome: TriggerOutcome,
+}
+
+// shared between every pass a pipeline runs
+#[derive(Debug, Default, Serialize)]
+pub struct ConversionReport {
+    // in the order they ran
+    pub passes: Vec<&'static str>,
+    // object id -> how many were taken out
+    pub removed: BTreeMap<u16, usize>,
+    pub substituted: Vec<Substitution>,
+    // channel -> the 1.9 colour its objects ended up on, None if they went back to the default
+    pub color_channels: BTreeMap<u16, Option<Color>>,
+    pub triggers: Vec<TriggerChange>,
+    pub warnings: Vec<Warning>,
+}
+
+// the json written out after a validated run, the conversion report with the validation next to it
+#[derive(Serialize)]
+struct ValidatedReport<'a> {
+    #[serde(flatten)]
+    conversion: &'a ConversionReport,
+    validation: &'a ValidationReport,
+}
+
+// object id -> count, for working out what a pass removed
+pub(crate) fn id_counts(objects: &ObjectList) -> BTreeMap<u16, usize> {
+    let mut counts = BTreeMap::new();
+    for obj in objects.iter() {
+        *counts.entry(obj.id()).or_default() += 1;
+    }
+    counts
+}
+
+impl ConversionReport {
+    pub fn warn(&mut self, pass: &'static str, severity: Severity, message: String, x_pos: Option<f32>) {
+        self.warnings.push(Warning { pass, severity, message, x_pos, x_end: None });
+    }
+    
+    pub fn warn_range(&mut self, pass: &'static str, severity: Severity, message: String, x_start: f32, x_end: f32) {
+        self.warnings.push(Warning { pass, severity, message, x_pos: Some(x_start), x_end: Some(x_end) });
+    }
+    
+    pub fn remove(&mut self, id: u16, count: usize) {
+        if count > 0 {
+            *self.removed.entry(id).or_default() += count;
+        }
+    }
+    
+    // records whatever is missing compared to counts taken before a pass ran
+    pub(crate) fn remove_since(&mut self, before: &BTreeMap<u16, usize>, objects: &ObjectList) {
+        let after = id_counts(objects);
+        for (&id, &count) in before {
+            self.remove(id, count.saturating_sub(after.get(&id).copied().unwrap_or(0)));
+        }
+    }
+    
+    pub fn substitute(&mut self, from: u16, to: u16) {
+        match self.substituted.iter_mut().find(|s| s.from == from && s.to == to) {
+            Some(substitution) => substitution.count += 1,
+            None => self.substituted.push(Substitution { from, to, count: 1 }),
+        }
+    }
+    
+    pub fn trigger(&mut self, pass: &'static str, id: u16, x_pos: f32, outcome: TriggerOutcome) {
+        self.triggers.push(TriggerChange { pass, id, x_pos, outcome });
+    }
+    
+    pub fn removed_count(&self) -> usize {
+        self.removed.values().sum()
+    }
+    
+    // None when nothing needed a warning
+    pub fn worst_severity(&self) -> Option<Severity> {
+        self.warnings.iter().map(|w| w.severity).max()
+    }
+    
+    pub fn to_json(&self) -> EResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+    
+    pub fn to_json_with(&self, validation: &ValidationReport) -> EResult<String> {
+        Ok(serde_json::to_string_pretty(&ValidatedReport { conversion: self, validation })?)
+    }
+}
+
+// the summary shown to whoever made the level
+impl fmt::Display for ConversionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "passes: {}", self.passes.join(", "))?;
+        if !self.removed.is_empty() {
+            let ids: Vec<String> = self.removed.iter().map(|(id, count)| format!("{id} x{count}")).collect();
+            writeln!(f, "removed {} objects: {}", self.removed_count(), ids.join(", "))?;
+        }
+        for s in &self.substituted {
+            writeln!(f, "replaced object {} with {} x{}", s.from, s.to, s.count)?;
+        }
+        for (channel, color) in &self.color_channels {
+            match color {
+                Some(color) => writeln!(f, "colour channel {channel} now uses {color:?}")?,
+                None => writeln!(f, "colour channel {channel} has no 1.9 colour, objects use their default")?,
+            }
+        }
+        if !self.triggers.is_empty() {
+            let count = |f: fn(&TriggerOutcome) -> bool| self.triggers.iter().filter(|t| f(&t.outcome)).count();
+            writeln!(
+                f, "triggers: {} converted, {} baked, {} lost",
+                count(|o| matches!(o, TriggerOutcome::Converted { .. })),
+                count(|o| *o == TriggerOutcome::Baked),
+                count(|o| *o == TriggerOutcome::Lost),
+            )?;
+        }
+        if !self.warnings.is_empty() {
+            writeln!(f, "warnings:")?;
+        }
+        for warning in &self.warnings {
+            write!(f, "  {:?} [{}] {}", warning.severity, warning.pass, warning.message)?;
+            match (warning.x_pos, warning.x_end) {
+                (Some(start), Some(end)) => writeln!(f, " from x {start} to {end}")?,
+                (Some(x), None) => writeln!(f, " at x {x}")?,
+                _ => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn report() {
+        let mut report = ConversionReport::default();
+        report.passes.push("colors");
+        let objects = ObjectList::from_raw_str("kA2,0;1,1,2,15,3,15;1,1,2,15,3,15;1,8,2,45,3,15;").unwrap();
+        let before = id_counts(&objects);
+        let after = ObjectList::from_raw_str("kA2,0;1,1,2,15,3,15;").unwrap();
+        report.remove_since(&before, &after);
+        report.substitute(1022, 84);
+        report.substitute(1022, 84);
+        report.color_channels.insert(2, Some(Color::Col2));
+        report.color_channels.insert(60, None);
+        report.trigger("colors", 899, 30., TriggerOutcome::Converted { to: 718 });
+        report.warn("colors", Severity::Visual, "colour trigger for channel 60 removed".to_string(), Some(60.));
+        report.warn_range("gamemodes", Severity::Gameplay, "Robot replaced with Cube".to_string(), 100., 300.);
+        
+        assert_eq!(report.removed, BTreeMap::from([(1, 1), (8, 1)]));
+        assert_eq!(report.substituted, vec![Substitution { from: 1022, to: 84, count: 2 }]);
+        assert_eq!(report.worst_severity(), Some(Severity::Gameplay));
+        
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["removed"]["8"], 1);
+        assert_eq!(json["color_channels"]["2"], "Col2");
+        assert_eq!(json["color_channels"]["60"], serde_json::Value::Null);
+        assert_eq!(json["triggers"][0]["outcome"], "converted");
+        assert_eq!(json["triggers"][0]["to"], 718);
+        assert_eq!(json["warnings"][0]["severity"], "visual");
+        assert_eq!(json["warnings"][0]["x_end"], serde_json::Value::Null);
+        assert_eq!(json["warnings"][1]["x_pos"], 100.);
+        assert_eq!(json["warnings"][1]["x_end"], 300.);
+        
+        let summary = report.to_string();
+        assert!(summary.contains("removed 2 objects: 1 x1, 8 x1"));
+        assert!(summary.contains("triggers: 1 converted, 0 baked, 0 lost"));
+        assert!(summary.contains("Visual [colors] colour trigger for channel 60 removed at x 60"));
+        assert!(summary.contains("Gameplay [gamemodes] Robot replaced with Cube from x 100 to 300"));
+    }
+}
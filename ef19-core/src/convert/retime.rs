@@ -0,0 +1,113 @@
+use crate::models::level::ObjectList;
+use crate::models::timeline::{Speed, Timeline, SPEEDS_19, SPEEDS_22};
+
+const START_POS_ID: u16 = 31;
+const SPEED_KEY: &str = "kA4";
+
+#[derive(Debug, Default)]
+pub struct RetimeReport {
+    // how much 4x sections got scaled by (less than 1, they get squashed)
+    pub stretch_factor: f32,
+    // 4x sections as (start, end) in the original level, end is None for the last section
+    pub sections: Vec<(f32, Option<f32>)>,
+    // how much shorter the level is afterwards
+    pub shortened_by: f32,
+    // 4x portals swapped for 3x ones
+    pub portals: usize,
+}
+
+fn is_fastest_setting(value: Option<&str>) -> bool {
+    value.and_then(|v| v.parse().ok()).and_then(Speed::from_setting) == Some(Speed::Fastest)
+}
+
+// swaps 4x for 3x and squashes every 4x section so it takes as long to play as it did before
+pub fn retime_fastest(objects: &mut ObjectList) -> RetimeReport {
+    let factor = SPEEDS_19.speed(Speed::Faster) / SPEEDS_22.speed(Speed::Fastest);
+    let timeline = Timeline::new(objects, SPEEDS_22);
+    let segments = timeline.segments();
+    let mut report = RetimeReport { stretch_factor: factor, ..Default::default() };
+    if !segments.iter().any(|seg| seg.speed == Speed::Fastest) {
+        return report;
+    }
+    
+    // where each segment starts once everything before it has been squashed
+    let mut new_starts = Vec::with_capacity(segments.len());
+    let mut shift = 0.;
+    for seg in segments {
+        new_starts.push(seg.x_start - shift);
+        if seg.speed == Speed::Fastest {
+            report.sections.push((seg.x_start, Some(seg.x_end).filter(|x| x.is_finite())));
+            if seg.x_end.is_finite() {
+                shift += (seg.x_end - seg.x_start) * (1. - factor);
+            }
+        }
+    }
+    let retimed_x = |x: f32| {
+        let i = segments.iter().rposition(|seg| seg.x_start <= x).unwrap_or(0);
+        let scale = if segments[i].speed == Speed::Fastest { factor } else { 1. };
+        new_starts[i] + (x - segments[i].x_start) * scale
+    };
+    
+    let level_end = objects.objects().iter().map(|obj| obj.x_pos()).fold(0., f32::max);
+    report.shortened_by = level_end - retimed_x(level_end);
+    
+    if is_fastest_setting(objects.header_value(SPEED_KEY)) {
+        objects.set_header_value(SPEED_KEY, Speed::Faster.setting().to_string());
+    }
+    for obj in objects.objects_mut().iter_mut() {
+        obj.set_x_pos(retimed_x(obj.x_pos()));
+        if obj.id() == Speed::Fastest.portal_id() {
+            obj.set_id(Speed::Faster.portal_id());
+            report.portals += 1;
+        }
+        if obj.id() == START_POS_ID && is_fastest_setting(obj.raw(SPEED_KEY)) {
+            obj.set_other(SPEED_KEY, Speed::Faster.setting().to_string());
+        }
+    }
+    
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn squashes_fastest_sections() {
+        // 4x from 100 to 676, which is exactly one second
+        let mut objects = ObjectList::from_raw_str(
+            "kA4,0;1,1,2,50,3,15;1,1334,2,100,3,15;1,1,2,388,3,15;1,203,2,676,3,15;1,1,2,1000,3,15;"
+        ).unwrap();
+        let report = retime_fastest(&mut objects);
+        
+        let xs: Vec<f32> = objects.objects().iter().map(|obj| obj.x_pos()).collect();
+        assert_eq!(xs, vec![50., 100., 334., 568., 892.]);
+        assert_eq!(objects.objects()[1].id(), 203);
+        
+        assert_eq!(report.sections, vec![(100., Some(676.))]);
+        assert_eq!(report.stretch_factor, 0.8125);
+        assert_eq!(report.shortened_by, 108.);
+        assert_eq!(report.portals, 1);
+    }
+    
+    #[test]
+    fn fastest_start_speed() {
+        let mut objects = ObjectList::from_raw_str(
+            "kA4,4;1,1,2,576,3,15;1,201,2,1152,3,15;1,1,2,1200,3,15;"
+        ).unwrap();
+        retime_fastest(&mut objects);
+        
+        assert_eq!(objects.header_value("kA4"), Some("3"));
+        let xs: Vec<f32> = objects.objects().iter().map(|obj| obj.x_pos()).collect();
+        assert_eq!(xs, vec![468., 936., 984.]);
+    }
+    
+    #[test]
+    fn untouched_without_fastest() {
+        let mut objects = ObjectList::from_raw_str("kA4,0;1,203,2,100,3,15;1,1,2,300,3,15;").unwrap();
+        let report = retime_fastest(&mut objects);
+        
+        assert!(report.sections.is_empty());
+        assert_eq!(objects.objects()[1].x_pos(), 300.);
+    }
+}
@@ -0,0 +1,209 @@
+use serde::Serialize;
+use crate::convert::report::Severity;
+use crate::models::level::{LevelSettings, ObjectList};
+use crate::models::object::{is_trigger, LevelObject};
+use crate::models::trigger::{Trigger, TriggerKind};
+use crate::models::version::GameVersion;
+use crate::errors::{Error, EResult};
+
+// the 1.9 editor won't open anything with more than this
+const OBJECT_LIMIT_19: usize = 40_000;
+// main colour channel, 2.0 and up
+const MAIN_COLOR_KEY: &str = "21";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum Problem {
+    UnknownObject { id: u16 },
+    // keys 1.9 ignores, all of the ones on a single object
+    UnsupportedKeys { id: u16, keys: Vec<String> },
+    // a colour channel 1.9 doesn't have
+    ColorOutOfRange { id: u16, channel: u16 },
+    TooManyObjects { count: usize, limit: usize },
+    NonAsciiName,
+    NonAsciiDescription,
+    UnknownHeaderKey { key: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub problem: Problem,
+    // None for problems with the level rather than an object
+    pub position: Option<(f32, f32)>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    fn push(&mut self, severity: Severity, problem: Problem, position: Option<(f32, f32)>) {
+        self.issues.push(Issue { severity, problem, position });
+    }
+    
+    pub fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(move |issue| issue.severity == severity)
+    }
+    
+    pub fn crashes(&self) -> impl Iterator<Item = &Issue> {
+        self.with_severity(Severity::Crash)
+    }
+    
+    pub fn has_crashes(&self) -> bool {
+        self.crashes().next().is_some()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+    
+    // for refusing uploads, visual and gameplay problems are let through
+    pub fn ensure_no_crashes(&self) -> EResult<()> {
+        match self.crashes().count() {
+            0 => Ok(()),
+            crashes => Err(Error::Validation { crashes }),
+        }
+    }
+}
+
+// header keys 1.9 reads, kA for level settings and kS for the old colour format
+fn known_header_key(key: &str) -> bool {
+    let number = |prefix| key.strip_prefix(prefix).and_then(|n| n.parse::<u32>().ok());
+    match (number("kA"), number("kS")) {
+        (Some(n), _) => (1..=11).contains(&n),
+        (_, Some(n)) => (1..=37).contains(&n),
+        _ => false,
+    }
+}
+
+// 1.9 only had the four colour channels, the special ones and the player colours
+fn channel_in_19(channel: u16) -> bool {
+    matches!(channel, 1..=4 | 1000..=1007)
+}
+
+fn unsupported_keys(obj: &LevelObject) -> Vec<String> {
+    let mut keys: Vec<String> = obj.raw_keys()
+        .filter(|k| k.parse::<u32>().is_ok_and(|k| GameVersion::from_object_key(k) > GameVersion::V1_9))
+        .map(|k| k.to_string())
+        .collect();
+    // parsed into fields, so they aren't in raw_keys
+    if obj.z_layer().is_some() {
+        keys.push("24".to_string());
+    }
+    if obj.z_order().is_some() {
+        keys.push("25".to_string());
+    }
+    if obj.base_hsv().is_some() {
+        keys.push("41".to_string());
+    }
+    keys.sort_by_key(|k| k.parse::<u32>().unwrap_or(u32::MAX));
+    keys
+}
+
+fn check_object(report: &mut ValidationReport, obj: &LevelObject) {
+    let position = Some((obj.x_pos(), obj.y_pos()));
+    if GameVersion::from_object_id(obj.id()) > GameVersion::V1_9 {
+        report.push(Severity::Crash, Problem::UnknownObject { id: obj.id() }, position);
+        return;
+    }
+    
+    let keys = unsupported_keys(obj);
+    if !keys.is_empty() {
+        // a trigger losing a setting fires differently, anything else just looks different
+        let severity = if is_trigger(obj.id()) { Severity::Gameplay } else { Severity::Visual };
+        report.push(severity, Problem::UnsupportedKeys { id: obj.id(), keys }, position);
+    }
+    
+    let channel = match Trigger::from_object(obj).map(|t| t.kind) {
+        Some(TriggerKind::Color(color)) => Some(color.channel),
+        Some(TriggerKind::Pulse(pulse)) if !pulse.target_is_group => pulse.target,
+        _ => obj.raw(MAIN_COLOR_KEY).and_then(|v| v.parse().ok()).filter(|&c| c != 0),
+    };
+    if let Some(channel) = channel.filter(|&c| !channel_in_19(c)) {
+        report.push(Severity::Visual, Problem::ColorOutOfRange { id: obj.id(), channel }, position);
+    }
+}
+
+// everything 1.9 would choke on or show differently, in level order
+pub fn validate_for_19(objects: &ObjectList, settings: &LevelSettings) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    
+    if !settings.name.is_ascii() {
+        report.push(Severity::Visual, Problem::NonAsciiName, None);
+    }
+    if !settings.description.is_ascii() {
+        report.push(Severity::Visual, Problem::NonAsciiDescription, None);
+    }
+    if objects.len() > OBJECT_LIMIT_19 {
+        report.push(Severity::Crash, Problem::TooManyObjects { count: objects.len(), limit: OBJECT_LIMIT_19 }, None);
+    }
+    
+    let mut header_keys: Vec<&str> = objects.header_keys().filter(|k| !known_header_key(k)).collect();
+    header_keys.sort_unstable();
+    for key in header_keys {
+        report.push(Severity::Visual, Problem::UnknownHeaderKey { key: key.to_string() }, None);
+    }
+    
+    for obj in objects.iter() {
+        check_object(&mut report, obj);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::level::Password;
+    use crate::models::level::Song;
+    
+    fn settings(name: &str) -> LevelSettings {
+        LevelSettings { name: name.to_string(), description: String::new(), song: Song::Official(0), password: Password::NoCopy }
+    }
+    
+    #[test]
+    fn clean_level() {
+        let objects = ObjectList::from_raw_str("kA2,0,kS29,1;1,1,2,15,3,15;1,221,2,0,3,0,7,0;").unwrap();
+        let report = validate_for_19(&objects, &settings("awawa"));
+        assert!(report.is_empty());
+        assert!(report.ensure_no_crashes().is_ok());
+    }
+    
+    #[test]
+    fn problems() {
+        let objects = ObjectList::from_raw_str(concat!(
+            "kA2,0,kS38,1_0,kA22,1;",
+            "1,1,2,15,3,15,25,2;",
+            "1,1933,2,45,3,15;",
+            "1,901,2,75,3,15,51,2;",
+            "1,29,2,105,3,15,7,255,62,1;",
+            "1,1,2,135,3,15,21,50;",
+        )).unwrap();
+        let report = validate_for_19(&objects, &settings("awawa ☆"));
+        
+        let problems: Vec<(Severity, Problem)> = report.issues.iter()
+            .map(|issue| (issue.severity, issue.problem.clone()))
+            .collect();
+        assert_eq!(problems, vec![
+            (Severity::Visual, Problem::NonAsciiName),
+            (Severity::Visual, Problem::UnknownHeaderKey { key: "kA22".to_string() }),
+            (Severity::Visual, Problem::UnknownHeaderKey { key: "kS38".to_string() }),
+            (Severity::Visual, Problem::UnsupportedKeys { id: 1, keys: vec!["25".to_string()] }),
+            (Severity::Crash, Problem::UnknownObject { id: 1933 }),
+            (Severity::Crash, Problem::UnknownObject { id: 901 }),
+            (Severity::Gameplay, Problem::UnsupportedKeys { id: 29, keys: vec!["62".to_string()] }),
+            (Severity::Visual, Problem::UnsupportedKeys { id: 1, keys: vec!["21".to_string()] }),
+            (Severity::Visual, Problem::ColorOutOfRange { id: 1, channel: 50 }),
+        ]);
+        assert_eq!(report.crashes().next().unwrap().position, Some((45., 15.)));
+        assert!(matches!(report.ensure_no_crashes(), Err(Error::Validation { crashes: 2 })));
+        assert_eq!(report.issues.iter().map(|issue| issue.severity).max(), Some(Severity::Crash));
+        
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["issues"][4]["severity"], "crash");
+        assert_eq!(json["issues"][4]["problem"], "unknown_object");
+        assert_eq!(json["issues"][4]["id"], 1933);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::models::level::ObjectList;
+use crate::models::object::{LevelObject, ObjectVariant};
+
+#[derive(Debug, Default)]
+pub struct ZOrderReport {
+    // objects that ended up somewhere else in the list
+    pub moved: usize,
+}
+
+// layers go B4 = -3, B3 = -1, B2 = 1, B1 = 3, T1 = 5, T2 = 7, T3 = 9
+const DEFAULT_Z_LAYER: i8 = 5;
+
+// z layer then z order, the variant is passed in by callers that already looked it up
+pub(crate) fn draw_order(obj: &LevelObject, variant: Option<&ObjectVariant>) -> (i8, i32) {
+    let default_layer = variant.map_or(DEFAULT_Z_LAYER, |v| v.default_z_layer());
+    let default_z = variant.map_or(0, |v| v.default_z_order());
+    // 0 is what the editor saves for the default layer
+    let layer = obj.z_layer().filter(|&layer| layer != 0).unwrap_or(default_layer);
+    (layer, obj.z_order().unwrap_or(default_z))
+}
+
+// 1.9 has no z layers or z order and draws roughly in list order, so the list
+// gets sorted by how 2.x would draw it and the z keys are dropped
+pub fn flatten_z_order(objects: &mut ObjectList) -> ZOrderReport {
+    let mut indexed: Vec<(usize, LevelObject)> = objects.objects_mut().drain(..).enumerate().collect();
+    // stable, so objects on the same layer keep their order
+    indexed.sort_by_key(|(_, obj)| draw_order(obj, ObjectVariant::from_id(obj.id()).as_ref()));
+    let moved = indexed.iter().enumerate().filter(|(to, (from, _))| to != from).count();
+    
+    for (_, mut obj) in indexed {
+        obj.set_z_layer(None);
+        obj.set_z_order(None);
+        objects.push(obj);
+    }
+    ZOrderReport { moved }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn flattens() {
+        let mut objects = ObjectList::from_raw_str(concat!(
+            "kA2,0;",
+            "1,1,2,15,3,15,25,5;",
+            "1,2,2,15,3,15;",
+            "1,3,2,15,3,15,24,-1;",
+            "1,4,2,15,3,15;",
+            "1,5,2,15,3,15,24,7;",
+            "1,6,2,15,3,15,24,1;",
+            "1,7,2,15,3,15,24,0,25,-2;",
+        )).unwrap();
+        let report = flatten_z_order(&mut objects);
+        
+        // B3, B2, then the default T1 by z order, then T2
+        let ids: Vec<u16> = objects.iter().map(|obj| obj.id()).collect();
+        assert_eq!(ids, vec![3, 6, 7, 2, 4, 1, 5]);
+        assert_eq!(report.moved, 7);
+        assert!(objects.iter().all(|obj| obj.z_layer().is_none() && obj.z_order().is_none()));
+    }
+}
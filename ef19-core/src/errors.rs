@@ -2,6 +2,7 @@ use std::fmt;
 use std::io::Error as IoError;
 use crate::codec::gdshare::GmdError;
 use crate::codec::ZipError;
+use crate::codec::server::{ServerError, TransportError};
 
 #[derive(Debug)]
 pub enum KeyError {
@@ -25,6 +26,12 @@ pub enum Error {
     Key(KeyError),
     Io(IoError),
     Base64(base64::DecodeError),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    Transport(TransportError),
+    Server(ServerError),
+    // the level still has problems that would crash 1.9
+    Validation { crashes: usize },
 }
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -34,6 +41,11 @@ impl fmt::Display for Error {
             Self::Key(e) => write!(f, "{e}"),
             Self::Io(e) => write!(f, "I/O error: {e}"),
             Self::Base64(e) => write!(f, "base64 decode error: {e}"),
+            Self::Json(e) => write!(f, "json error: {e}"),
+            Self::Toml(e) => write!(f, "toml error: {e}"),
+            Self::Transport(e) => write!(f, "request failed: {e}"),
+            Self::Server(e) => write!(f, "{e}"),
+            Self::Validation { crashes } => write!(f, "level has {crashes} problem(s) that would crash 1.9"),
         }
     }
 }
@@ -45,6 +57,11 @@ impl std::error::Error for Error {
             Self::Key(e) => Some(e),
             Self::Io(e) => Some(e),
             Self::Base64(e) => Some(e),
+            Self::Json(e) => Some(e),
+            Self::Toml(e) => Some(e),
+            Self::Transport(e) => Some(e),
+            Self::Server(e) => Some(e),
+            Self::Validation { .. } => None,
         }
     }
 }
@@ -68,6 +85,26 @@ impl From<base64::DecodeError> for Error {
         Self::Base64(e)
     }
 }
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Toml(e)
+    }
+}
+impl From<TransportError> for Error {
+    fn from(e: TransportError) -> Self {
+        Self::Transport(e)
+    }
+}
+impl From<ServerError> for Error {
+    fn from(e: ServerError) -> Self {
+        Self::Server(e)
+    }
+}
 impl From<ZipError> for Error {
     fn from(e: ZipError) -> Self {
         match e {
@@ -1,3 +1,4 @@
 pub mod models;
 pub mod codec;
+pub mod convert;
 pub mod errors;
\ No newline at end of file
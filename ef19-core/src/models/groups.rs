@@ -0,0 +1,228 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::models::level::ObjectList;
+use crate::models::object::{is_trigger, LevelObject};
+use crate::models::trigger::{Trigger, TriggerKind};
+
+// the group a trigger acts on (key 51), 0 means no target,
+// read raw so triggers without a typed version still count
+pub(crate) fn target_group(obj: &LevelObject) -> Option<u16> {
+    match Trigger::from_object(obj) {
+        Some(trigger) => trigger.target_group(),
+        None => obj.raw("51").and_then(|v| v.parse().ok()).filter(|&g| g != 0),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Visible,
+    // toggled off before the player gets to it and never toggled back on
+    StartsHidden,
+    // toggled off before the player gets to it, then shown by a trigger later
+    ShownByTrigger,
+}
+
+// which objects are in which groups and which triggers act on them,
+// all by index into the object list it was built from
+#[derive(Debug, Default)]
+pub struct GroupIndex {
+    members: HashMap<u16, Vec<usize>>,
+    targeted_by: HashMap<u16, Vec<usize>>,
+    toggled_on: HashMap<u16, Vec<usize>>,
+    toggled_off: HashMap<u16, Vec<usize>>,
+    spawned_by: HashMap<u16, Vec<usize>>,
+    visibility: HashMap<u16, Visibility>,
+}
+
+impl GroupIndex {
+    pub fn new(objects: &ObjectList) -> GroupIndex {
+        let mut index = GroupIndex::default();
+        let objs = objects.objects();
+        
+        for (i, obj) in objs.iter().enumerate() {
+            for group in obj.groups() {
+                index.members.entry(group).or_default().push(i);
+            }
+            if !is_trigger(obj.id()) {
+                continue;
+            }
+            let Some(group) = target_group(obj) else { continue; };
+            index.targeted_by.entry(group).or_default().push(i);
+            match Trigger::from_object(obj).map(|t| t.kind) {
+                Some(TriggerKind::Toggle(toggle)) if toggle.activate => {
+                    index.toggled_on.entry(group).or_default().push(i);
+                },
+                Some(TriggerKind::Toggle(_)) => {
+                    index.toggled_off.entry(group).or_default().push(i);
+                },
+                Some(TriggerKind::Spawn(_)) => {
+                    index.spawned_by.entry(group).or_default().push(i);
+                },
+                _ => (),
+            }
+        }
+        
+        for (&group, toggles) in &index.toggled_off {
+            let leftmost = index.leftmost_member(objects, group).unwrap_or(f32::INFINITY);
+            let hidden_at_start = toggles.iter()
+                .map(|&i| &objs[i])
+                .filter_map(|obj| Some((obj.x_pos(), Trigger::from_object(obj)?)))
+                .any(|(x_pos, trigger)| trigger.activation.fires_on_pass() && x_pos <= leftmost);
+            if hidden_at_start {
+                let visibility = if index.toggled_on.contains_key(&group) {
+                    Visibility::ShownByTrigger
+                } else {
+                    Visibility::StartsHidden
+                };
+                index.visibility.insert(group, visibility);
+            }
+        }
+        
+        index
+    }
+    
+    pub fn members(&self, group: u16) -> &[usize] {
+        self.members.get(&group).map_or(&[], |v| v.as_slice())
+    }
+    
+    pub fn targeted_by(&self, group: u16) -> &[usize] {
+        self.targeted_by.get(&group).map_or(&[], |v| v.as_slice())
+    }
+    
+    pub fn toggled_on_by(&self, group: u16) -> &[usize] {
+        self.toggled_on.get(&group).map_or(&[], |v| v.as_slice())
+    }
+    
+    pub fn toggled_off_by(&self, group: u16) -> &[usize] {
+        self.toggled_off.get(&group).map_or(&[], |v| v.as_slice())
+    }
+    
+    pub fn spawned_by(&self, group: u16) -> &[usize] {
+        self.spawned_by.get(&group).map_or(&[], |v| v.as_slice())
+    }
+    
+    // x of the first object in the group the player could see, triggers don't count
+    pub fn leftmost_member(&self, objects: &ObjectList, group: u16) -> Option<f32> {
+        self.members(group).iter()
+            .map(|&i| &objects.objects()[i])
+            .filter(|obj| !is_trigger(obj.id()))
+            .map(|obj| obj.x_pos())
+            .reduce(f32::min)
+    }
+    
+    pub fn is_toggled(&self, group: u16) -> bool {
+        self.toggled_on.contains_key(&group) || self.toggled_off.contains_key(&group)
+    }
+    
+    pub fn is_spawned(&self, group: u16) -> bool {
+        self.spawned_by.contains_key(&group)
+    }
+    
+    // every group that ends up spawned when this one is, in the order they're reached
+    pub fn spawn_chain(&self, objects: &ObjectList, group: u16) -> Vec<u16> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::from([group]);
+        let mut queue = VecDeque::from([group]);
+        while let Some(current) = queue.pop_front() {
+            for &i in self.members(current) {
+                let Some(Trigger { kind: TriggerKind::Spawn(spawn), .. }) = Trigger::from_object(&objects.objects()[i]) else {
+                    continue;
+                };
+                if let Some(next) = spawn.target_group.filter(|g| seen.insert(*g)) {
+                    chain.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        chain
+    }
+    
+    pub fn visibility(&self, group: u16) -> Visibility {
+        self.visibility.get(&group).copied().unwrap_or(Visibility::Visible)
+    }
+    
+    // an object in several groups is as hidden as its most hidden group
+    pub fn object_visibility(&self, obj: &LevelObject) -> Visibility {
+        let mut visibility = Visibility::Visible;
+        for group in obj.groups() {
+            match self.visibility(group) {
+                Visibility::StartsHidden => { return Visibility::StartsHidden; },
+                Visibility::ShownByTrigger => { visibility = Visibility::ShownByTrigger; },
+                Visibility::Visible => (),
+            }
+        }
+        visibility
+    }
+    
+    // objects 1.9 would show from the start even though they start out hidden
+    pub fn hidden_objects(&self, objects: &ObjectList) -> Vec<usize> {
+        objects.objects().iter()
+            .enumerate()
+            .filter(|(_, obj)| !is_trigger(obj.id()))
+            .filter(|(_, obj)| self.object_visibility(obj) != Visibility::Visible)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn index(raw: &str) -> (ObjectList, GroupIndex) {
+        let objects = ObjectList::from_raw_str(raw).unwrap();
+        let index = GroupIndex::new(&objects);
+        (objects, index)
+    }
+    
+    #[test]
+    fn members_and_triggers() {
+        let (_, index) = index(concat!(
+            "kA2,0;",
+            "1,1,2,300,3,15,57,1.2;",
+            "1,1,2,330,3,15,57,2;",
+            "1,901,2,100,3,15,51,2;",
+            "1,1049,2,100,3,15,51,1,56,1;",
+            "1,1268,2,100,3,15,51,3;",
+        ));
+        assert_eq!(index.members(1), &[0]);
+        assert_eq!(index.members(2), &[0, 1]);
+        assert_eq!(index.targeted_by(2), &[2]);
+        assert_eq!(index.toggled_on_by(1), &[3]);
+        assert!(index.toggled_off_by(1).is_empty());
+        assert!(index.is_spawned(3));
+        assert!(!index.is_toggled(2));
+    }
+    
+    #[test]
+    fn spawn_chain() {
+        let (objects, index) = index(concat!(
+            "kA2,0;",
+            "1,1268,2,100,3,15,57,1,62,1,51,2;",
+            "1,1268,2,100,3,15,57,2,62,1,51,3;",
+            // loops back round, shouldn't be followed twice
+            "1,1268,2,100,3,15,57,3,62,1,51,1;",
+        ));
+        assert_eq!(index.spawn_chain(&objects, 1), vec![2, 3]);
+        assert_eq!(index.spawn_chain(&objects, 3), vec![1, 2]);
+    }
+    
+    #[test]
+    fn hidden_objects() {
+        let (objects, index) = index(concat!(
+            "kA2,0;",
+            "1,1,2,300,3,15,57,1;",
+            "1,1,2,330,3,15,57,2;",
+            "1,1,2,360,3,15,57,3;",
+            "1,1,2,390,3,15;",
+            "1,1049,2,0,3,15,51,1;",
+            "1,1049,2,0,3,15,51,2;",
+            "1,1049,2,345,3,15,51,2,56,1;",
+            // too late to hide it before it's seen
+            "1,1049,2,400,3,15,51,3;",
+        ));
+        assert_eq!(index.visibility(1), Visibility::StartsHidden);
+        assert_eq!(index.visibility(2), Visibility::ShownByTrigger);
+        assert_eq!(index.visibility(3), Visibility::Visible);
+        assert_eq!(index.hidden_objects(&objects), vec![0, 1]);
+    }
+}
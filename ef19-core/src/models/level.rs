@@ -1,18 +1,35 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 use crate::models::object::LevelObject;
+use crate::models::timeline::{Timeline, SPEEDS_19};
+use crate::models::version::{detect_version, GameVersion};
 use crate::codec;
-use crate::errors::{KeyError, Error, EResult};
+use crate::codec::crypt::decode_level_password;
+use crate::errors::{Error, EResult, KeyError};
+
+const TWO_PLAYER_KEY: &str = "kA10";
+// objects hidden in low detail mode
+const HIGH_DETAIL_KEY: &str = "103";
 
 #[derive(Debug)]
-struct ObjectList {
+pub struct ObjectList {
     header: HashMap<String, String>,
     objects: Vec<LevelObject>,
 }
 
-impl ObjectList {
-    pub fn from_str(object_str: &str) -> EResult<Self> {
+impl FromStr for ObjectList {
+    type Err = Error;
+    
+    fn from_str(object_str: &str) -> EResult<Self> {
         let decompressed = codec::unzip_string(object_str)?;
-        let mut split = decompressed.split_terminator(";")
+        Self::from_raw_str(&decompressed)
+    }
+}
+
+impl ObjectList {
+    // same as from_str but for level strings that aren't gzipped
+    pub fn from_raw_str(raw_str: &str) -> EResult<Self> {
+        let mut split = raw_str.split_terminator(';')
             .map(|x| codec::deserialise_kv(x, ","));
         let header = match split.next() {
             Some(header) => {
@@ -31,11 +48,11 @@ impl ObjectList {
     pub fn string(&self) -> EResult<String> {
         let mut object_str = String::new();
         object_str.push_str(&codec::serialise_kv(&self.header, ","));
-        object_str.push_str(";");
+        object_str.push(';');
         for obj in &self.objects {
             let map = obj.map();
             object_str.push_str(&codec::serialise_kv(&map, ","));
-            object_str.push_str(";");
+            object_str.push(';');
         }
         
         Ok(codec::zip_string(&object_str)?)
@@ -44,41 +61,345 @@ impl ObjectList {
     pub fn into_string(self) -> EResult<String> {
         let mut object_str = String::new();
         object_str.push_str(&codec::serialise_kv(&self.header, ","));
-        object_str.push_str(";");
+        object_str.push(';');
         for obj in self.objects {
             let map = obj.into_map();
             object_str.push_str(&codec::serialise_kv(&map, ","));
-            object_str.push_str(";");
+            object_str.push(';');
         }
         
         Ok(codec::zip_string(&object_str)?)
     }
+    
+    pub fn objects(&self) -> &[LevelObject] {
+        &self.objects
+    }
+    
+    pub(crate) fn objects_mut(&mut self) -> &mut Vec<LevelObject> {
+        &mut self.objects
+    }
+    
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+    
+    pub fn iter(&self) -> impl Iterator<Item = &LevelObject> {
+        self.objects.iter()
+    }
+    
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut LevelObject> {
+        self.objects.iter_mut()
+    }
+    
+    pub fn filter<F>(&self, mut predicate: F) -> impl Iterator<Item = &LevelObject>
+    where F: FnMut(&LevelObject) -> bool {
+        self.objects.iter().filter(move |obj| predicate(obj))
+    }
+    
+    pub fn get(&self, index: usize) -> Option<&LevelObject> {
+        self.objects.get(index)
+    }
+    
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut LevelObject> {
+        self.objects.get_mut(index)
+    }
+    
+    // objects later in the list are drawn on top when z layer and order are the same
+    pub fn push(&mut self, obj: LevelObject) {
+        self.objects.push(obj);
+    }
+    
+    pub fn insert(&mut self, index: usize, obj: LevelObject) {
+        self.objects.insert(index, obj);
+    }
+    
+    pub fn remove(&mut self, index: usize) -> LevelObject {
+        self.objects.remove(index)
+    }
+    
+    pub fn retain<F>(&mut self, predicate: F)
+    where F: FnMut(&LevelObject) -> bool {
+        self.objects.retain(predicate);
+    }
+    
+    // level settings (speed, gamemode, colours and so on) from the header object
+    pub fn header_value(&self, key: &str) -> Option<&str> {
+        self.header.get(key).map(|v| v.as_str())
+    }
+    
+    pub fn header_keys(&self) -> impl Iterator<Item = &str> {
+        self.header.keys().map(|k| k.as_str())
+    }
+    
+    pub fn set_header_value(&mut self, key: &str, val: String) {
+        self.header.insert(key.to_string(), val);
+    }
+    
+    pub fn remove_header_value(&mut self, key: &str) -> Option<String> {
+        self.header.remove(key)
+    }
+    
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.objects.iter_mut().for_each(|obj| obj.translate(dx, dy));
+    }
+    
+    pub fn mirror_horizontally(&mut self, axis_x: f32) {
+        self.objects.iter_mut().for_each(|obj| obj.mirror_horizontally(axis_x));
+    }
+    
+    pub fn rotate_around(&mut self, x: f32, y: f32, degrees: f32) {
+        self.objects.iter_mut().for_each(|obj| obj.rotate_around(x, y, degrees));
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Song {
     Official(u32),
     Custom(u32),
 }
 
+// who can copy the level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Password {
+    #[default]
+    NoCopy,
+    FreeCopy,
+    // up to 6 digits, see Password::code
+    Code(u32),
+}
+
+impl Password {
+    pub const MAX_CODE: u32 = 999_999;
+    
+    // None for codes the game can't show, and for 0 and 1 since they'd be
+    // saved as no copy and free copy
+    pub fn code(code: u32) -> Option<Password> {
+        (2..=Password::MAX_CODE).contains(&code).then_some(Password::Code(code))
+    }
+    
+    // 0, 1, or a code. 2.x puts a 1 in front of codes and pads them to 6 digits
+    pub fn from_plain(password: &str) -> Option<Password> {
+        let password = password.trim();
+        if password.is_empty() {
+            return Some(Password::NoCopy);
+        }
+        let code: u32 = password.parse().ok()?;
+        match code {
+            0 => Some(Password::NoCopy),
+            1 => Some(Password::FreeCopy),
+            _ if password.len() == 7 && password.starts_with('1') => Password::code(code - 1_000_000),
+            _ => Password::code(code),
+        }
+    }
+    
+    // key 27 in a server reply, xored and base64 encoded from 2.0 on, plain before
+    pub fn from_server(password: &str) -> EResult<Password> {
+        let plain = if password.bytes().all(|b| b.is_ascii_digit()) {
+            password.to_string()
+        } else {
+            decode_level_password(password)?
+        };
+        Password::from_plain(&plain)
+            .ok_or_else(|| KeyError::Invalid { key: "27".to_string(), val: password.to_string() }.into())
+    }
+    
+    // how 2.x writes it before encoding
+    pub fn to_plain(self) -> String {
+        match self {
+            Password::NoCopy => "0".to_string(),
+            Password::FreeCopy => "1".to_string(),
+            Password::Code(code) => format!("1{code:06}"),
+        }
+    }
+    
+    // 1.9 uploads send codes as they are
+    pub fn to_19(self) -> String {
+        match self {
+            Password::NoCopy => "0".to_string(),
+            Password::FreeCopy => "1".to_string(),
+            Password::Code(code) => code.to_string(),
+        }
+    }
+}
+
+// what gets uploaded alongside the objects
+#[derive(Debug, Clone)]
+pub struct LevelSettings {
+    pub name: String,
+    pub description: String,
+    pub song: Song,
+    pub password: Password,
+}
+
 #[derive(Debug)]
 pub struct Level {
-    name: String,
-    description: String,
-    object_str: String,
-    object_list: Option<ObjectList>,
-    song: Song,
-    version: u32,
-    length: u32,
-    is_two_player: bool,
-    object_count: u32,
-    has_low_detail: bool,
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) object_str: String,
+    pub(crate) object_list: Option<ObjectList>,
+    pub(crate) song: Song,
+    pub(crate) password: Password,
+    pub(crate) version: u32,
+    // what the level was last saved with, 0 when we don't know
+    pub(crate) game_version: u32,
+    pub(crate) binary_version: u32,
+    pub(crate) length: u32,
+    pub(crate) is_two_player: bool,
+    pub(crate) object_count: u32,
+    pub(crate) has_low_detail: bool,
 }
 
 impl Level {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
     
-    // pub fn from_server_string(string: &str) -> Option<Level> {}
+    pub fn settings(&self) -> LevelSettings {
+        LevelSettings {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            song: self.song.clone(),
+            password: self.password,
+        }
+    }
     
-    // pub fn from_server_map(map: &HashMap<String, String>) -> Option<Level> {}
+    pub fn song(&self) -> &Song {
+        &self.song
+    }
+    
+    pub fn set_song(&mut self, song: Song) {
+        self.song = song;
+    }
+    
+    pub fn password(&self) -> Password {
+        self.password
+    }
+    
+    pub fn set_password(&mut self, password: Password) {
+        self.password = password;
+    }
+    
+    // parses the object string the first time it's needed
+    pub fn object_list(&mut self) -> EResult<&mut ObjectList> {
+        if self.object_list.is_none() {
+            self.object_list = Some(self.object_str.parse()?);
+        }
+        Ok(self.object_list.as_mut().unwrap())
+    }
+    
+    // the (gzipped) object string, with any changes made to the parsed list
+    pub fn object_string(&self) -> EResult<String> {
+        match &self.object_list {
+            Some(objects) => objects.string(),
+            None => Ok(self.object_str.clone()),
+        }
+    }
+    
+    // oldest version the level could have been made in, levels last saved
+    // by 1.9 don't need their objects looked at
+    pub fn source_version(&mut self) -> EResult<GameVersion> {
+        let saved = GameVersion::from_game_version(self.game_version)
+            .or_else(|| GameVersion::from_binary_version(self.binary_version));
+        if saved == Some(GameVersion::V1_9) {
+            return Ok(GameVersion::V1_9);
+        }
+        // nothing in it can be newer than what saved it
+        let detected = detect_version(self.object_list()?);
+        Ok(saved.map_or(detected, |saved| detected.min(saved)))
+    }
+    
+    pub fn needs_conversion(&mut self) -> EResult<bool> {
+        Ok(self.source_version()? != GameVersion::V1_9)
+    }
+    
+    // refreshes the metadata sent on upload so it matches the (converted) objects
+    pub fn recompute_metadata(&mut self) -> EResult<()> {
+        let objects = self.object_list()?;
+        
+        let length = Timeline::new(objects, SPEEDS_19).length(objects);
+        let object_count = objects.objects().len() as u32;
+        let is_two_player = objects.header_value(TWO_PLAYER_KEY) == Some("1");
+        let has_low_detail = objects.objects().iter()
+            .any(|obj| obj.raw(HIGH_DETAIL_KEY) == Some("1"));
+        
+        self.length = length as u32;
+        self.object_count = object_count;
+        self.is_two_player = is_two_player;
+        self.has_low_detail = has_low_detail;
+        Ok(())
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn metadata() {
+        let raw = "kA4,0,kA10,1;1,1,2,15,3,15;1,1,2,15000,3,15,103,1;1,8,2,45,3,15;";
+        let mut level = Level {
+            name: "awawa".to_string(),
+            description: String::new(),
+            object_str: codec::zip_string(raw).unwrap(),
+            object_list: None,
+            song: Song::Official(0),
+            password: Password::default(),
+            version: 1,
+            game_version: 21,
+            binary_version: 35,
+            length: 0,
+            is_two_player: false,
+            object_count: 0,
+            has_low_detail: false,
+        };
+        level.recompute_metadata().unwrap();
+        
+        assert_eq!(level.length, 2);
+        assert_eq!(level.object_count, 3);
+        assert!(level.is_two_player);
+        assert!(level.has_low_detail);
+        // high detail (103) is a 2.0 key
+        assert_eq!(level.source_version().unwrap(), GameVersion::V2_0);
+        assert!(level.needs_conversion().unwrap());
+    }
+    
+    #[test]
+    fn editing() {
+        let mut objects = ObjectList::from_raw_str("kA2,0;1,1,2,15,3,15;1,8,2,45,3,15;1,1,2,75,3,15;").unwrap();
+        objects.insert(1, LevelObject::new(18, 15., 15.));
+        objects.retain(|obj| obj.id() != 8);
+        objects.mirror_horizontally(45.);
+        objects.set_header_value("kA4", "1".to_string());
+        
+        let positions: Vec<(u16, f32)> = objects.iter().map(|obj| (obj.id(), obj.x_pos())).collect();
+        assert_eq!(positions, vec![(1, 75.), (18, 75.), (1, 15.)]);
+        assert_eq!(objects.filter(|obj| obj.flip().0).count(), 3);
+        assert_eq!(objects.header_value("kA4"), Some("1"));
+    }
+    
+    #[test]
+    fn passwords() {
+        assert_eq!(Password::from_server("Ag==").unwrap(), Password::NoCopy);
+        assert_eq!(Password::from_server("Aw==").unwrap(), Password::FreeCopy);
+        assert_eq!(Password::from_server("AwYDBgUABQ==").unwrap(), Password::Code(123));
+        // plain ones from before 2.0
+        assert_eq!(Password::from_server("1").unwrap(), Password::FreeCopy);
+        assert_eq!(Password::from_server("4321").unwrap(), Password::Code(4321));
+        assert!(Password::from_server("YQ==").is_err());
+        assert!(Password::from_server("12345678").is_err());
+        assert_eq!(Password::code(999_999), Some(Password::Code(999_999)));
+        assert_eq!(Password::code(1_000_000), None);
+        assert_eq!(Password::code(0), None);
+        assert_eq!(Password::code(1), None);
+        assert_eq!(Password::from_plain("1000001"), None);
+        assert!(Password::from_server("1000000").is_err());
+        
+        assert_eq!(Password::Code(123).to_plain(), "1000123");
+        assert_eq!(Password::Code(123).to_19(), "123");
+        assert_eq!(Password::FreeCopy.to_19(), "1");
+    }
+}
@@ -77,6 +77,7 @@ macro_rules! attr_from_map {
 
 pub(crate) use attr_from_map;
 
+#[allow(unused_macros)]
 macro_rules! attr_from_map_real {
     ($m:expr, $i:expr, bool) => {
         
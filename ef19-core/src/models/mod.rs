@@ -1,3 +1,8 @@
 pub mod level;
 pub mod object;
+pub mod timeline;
+pub mod groups;
+pub mod spatial;
+pub mod trigger;
+pub mod version;
 mod macros;
\ No newline at end of file
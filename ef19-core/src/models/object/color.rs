@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum Color {
     Player1 = 1,
     Player2 = 2,
@@ -37,4 +39,17 @@ impl Color {
             _ => None,
         }
     }
+    // the 2.0 channel the colour lives on
+    pub fn new_id(self) -> u16 {
+        match self {
+            Color::Player1 => 1005,
+            Color::Player2 => 1006,
+            Color::Col1 => 1,
+            Color::Col2 => 2,
+            Color::LightBG => 1007,
+            Color::Col3 => 3,
+            Color::Col4 => 4,
+            Color::DLine => 1003,
+        }
+    }
 }
\ No newline at end of file
@@ -1,12 +1,30 @@
 use std::collections::HashMap;
 use super::macros::attr_from_map;
 pub use color::Color;
+pub use variants::{ObjectKind, ObjectVariant};
 use crate::codec::format::GdFormat;
-use crate::errors::{KeyError, Error, EResult};
+use crate::models::trigger::MOVE_TRIGGER_ID;
+use crate::errors::{KeyError, Error};
 
 mod variants;
 mod color;
 
+// keys parsed into LevelObject fields rather than kept in other_data
+const FIELD_KEYS: [&str; 12] = ["1", "2", "3", "4", "5", "6", "19", "22", "24", "25", "41", "43"];
+const MOVE_X_KEY: &str = "28";
+const MOVE_Y_KEY: &str = "29";
+
+// triggers from 1.9 (colour triggers) up to 2.1, 2.2 ones are by range so not exact
+pub fn is_trigger(id: u16) -> bool {
+    matches!(id,
+        29 | 30 | 104 | 105 | 221 | 717 | 718 | 743 | 744 | 899 | 900 | 901 | 915
+        | 1006 | 1007 | 1049 | 1268 | 1346 | 1347 | 1520 | 1585 | 1595 | 1611 | 1612
+        | 1613 | 1616 | 1811 | 1812 | 1814 | 1815 | 1817 | 1818 | 1819
+        | 1912..=1917 | 1931 | 1932 | 1934 | 1935 | 2015 | 2016 | 2062 | 2066..=2068
+        | 2899..=2925 | 3006..=3024 | 3029..=3033 | 3600..=3662
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct LevelObject {
     // properties we want fast access to
@@ -113,8 +131,200 @@ impl LevelObject {
         
         self.other_data
     }
+    
+    // an object with nothing set apart from what every object needs
+    pub fn new(id: u16, x_pos: f32, y_pos: f32) -> LevelObject {
+        LevelObject {
+            id,
+            x_pos,
+            y_pos,
+            flip_x: false,
+            flip_y: false,
+            rotation: 0.,
+            color: None,
+            z_layer: None,
+            z_order: None,
+            base_hsv: None,
+            other_data: HashMap::new(),
+        }
+    }
+    
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+    
+    pub fn set_id(&mut self, id: u16) {
+        self.id = id;
+    }
+    
+    pub fn x_pos(&self) -> f32 {
+        self.x_pos
+    }
+    
+    pub fn set_x_pos(&mut self, x_pos: f32) {
+        self.x_pos = x_pos;
+    }
+    
+    pub fn y_pos(&self) -> f32 {
+        self.y_pos
+    }
+    
+    pub fn set_y_pos(&mut self, y_pos: f32) {
+        self.y_pos = y_pos;
+    }
+    
+    pub fn position(&self) -> (f32, f32) {
+        (self.x_pos, self.y_pos)
+    }
+    
+    pub fn set_position(&mut self, x_pos: f32, y_pos: f32) {
+        self.x_pos = x_pos;
+        self.y_pos = y_pos;
+    }
+    
+    // degrees, clockwise
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+    
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+    
+    pub fn flip(&self) -> (bool, bool) {
+        (self.flip_x, self.flip_y)
+    }
+    
+    pub fn set_flip(&mut self, flip_x: bool, flip_y: bool) {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+    }
+    
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+    
+    pub fn set_color(&mut self, color: Option<Color>) {
+        self.color = color;
+    }
+    
+    pub fn base_hsv(&self) -> Option<&str> {
+        self.base_hsv.as_deref()
+    }
+    
+    pub fn set_base_hsv(&mut self, base_hsv: Option<String>) {
+        self.base_hsv = base_hsv;
+    }
+    
+    pub fn z_layer(&self) -> Option<i8> {
+        self.z_layer
+    }
+    
+    pub fn set_z_layer(&mut self, z_layer: Option<i8>) {
+        self.z_layer = z_layer;
+    }
+    
+    pub fn z_order(&self) -> Option<i32> {
+        self.z_order
+    }
+    
+    pub fn set_z_order(&mut self, z_order: Option<i32>) {
+        self.z_order = z_order;
+    }
+    
+    // 32 scales both ways, 2.2 added 128 and 129 for each axis on top of that
+    pub fn scale(&self) -> (f32, f32) {
+        let get = |key| self.raw(key).and_then(|v| v.parse::<f32>().ok()).unwrap_or(1.);
+        (get("32") * get("128"), get("32") * get("129"))
+    }
+    
+    // group ids are stored as a dot separated list
+    pub fn groups(&self) -> Vec<u16> {
+        match self.other_data.get("57") {
+            Some(v) => v.split('.').filter_map(|g| g.parse().ok()).collect(),
+            None => Vec::new(),
+        }
+    }
+    
+    // raw access to keys that aren't parsed into fields
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.other_data.get(key).map(|v| v.as_str())
+    }
+    
+    pub fn raw_keys(&self) -> impl Iterator<Item = &str> {
+        self.other_data.keys().map(|k| k.as_str())
+    }
+    
+    pub fn remove_raw(&mut self, key: &str) -> Option<String> {
+        self.other_data.remove(key)
+    }
+    
+    // parsed keys go through the same parsing as loading, so a bad value is an error
+    pub fn set_raw(&mut self, key: &str, val: String) -> Result<(), Error> {
+        if !FIELD_KEYS.contains(&key) {
+            self.set_other(key, val);
+            return Ok(());
+        }
+        let mut map = self.map();
+        // the old colour key wins when both are there
+        if key == "22" {
+            map.remove("19");
+        }
+        map.insert(key.to_string(), val);
+        *self = LevelObject::from_map(map)?;
+        Ok(())
+    }
+    
+    pub(crate) fn set_other(&mut self, key: &str, val: String) {
+        self.other_data.insert(key.to_string(), val);
+    }
+    
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.x_pos += dx;
+        self.y_pos += dy;
+    }
+    
+    // mirrors across the vertical line at axis_x, move triggers move the other way too
+    pub fn mirror_horizontally(&mut self, axis_x: f32) {
+        self.x_pos = 2. * axis_x - self.x_pos;
+        self.flip_x = !self.flip_x;
+        self.rotation = normalise_rotation(-self.rotation);
+        if self.id == MOVE_TRIGGER_ID {
+            if let Some(dx) = self.raw(MOVE_X_KEY).and_then(|v| v.parse::<f32>().ok()) {
+                self.set_other(MOVE_X_KEY, (-dx).gd_format());
+            }
+        }
+    }
+    
+    // clockwise like object rotation, move trigger offsets are rotated with it
+    pub fn rotate_around(&mut self, x: f32, y: f32, degrees: f32) {
+        (self.x_pos, self.y_pos) = rotate_point(self.x_pos - x, self.y_pos - y, degrees);
+        self.x_pos += x;
+        self.y_pos += y;
+        self.rotation = normalise_rotation(self.rotation + degrees);
+        if self.id == MOVE_TRIGGER_ID {
+            let offset = |key| self.raw(key).and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.);
+            let (dx, dy) = rotate_point(offset(MOVE_X_KEY), offset(MOVE_Y_KEY), degrees);
+            self.set_other(MOVE_X_KEY, dx.gd_format());
+            self.set_other(MOVE_Y_KEY, dy.gd_format());
+        }
+    }
+}
+
+// rounded so a quarter turn doesn't leave positions a hair off the grid
+fn rotate_point(x: f32, y: f32, degrees: f32) -> (f32, f32) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let round = |v: f32| (v * 1000.).round() / 1000. + 0.;
+    (round(x * cos + y * sin), round(y * cos - x * sin))
+}
+
+fn normalise_rotation(rotation: f32) -> f32 {
+    let rotation = rotation.rem_euclid(360.);
+    if rotation > 180. { rotation - 360. } else { rotation }
 }
 
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
     
@@ -255,5 +465,40 @@ mod tests {
         let obj = LevelObject::from_map(map).unwrap();
         assert_eq!(obj.color, Some(Color::DLine));
     }
+    
+    #[test]
+    fn raw_keys() {
+        let mut obj = LevelObject::new(1, 15., 15.);
+        obj.set_raw("57", "2.3".to_string()).unwrap();
+        obj.set_raw("2", "45".to_string()).unwrap();
+        obj.set_raw("22", "1003".to_string()).unwrap();
+        assert_eq!(obj.groups(), vec![2, 3]);
+        assert_eq!(obj.x_pos(), 45.);
+        assert_eq!(obj.color(), Some(Color::DLine));
+        assert_eq!(obj.raw("2"), None);
+        assert!(obj.set_raw("3", "awawa".to_string()).is_err());
+        assert_eq!(obj.remove_raw("57"), Some("2.3".to_string()));
+    }
+    
+    #[test]
+    fn transforms() {
+        let mut obj = LevelObject::new(1, 45., 15.);
+        obj.translate(30., -15.);
+        assert_eq!(obj.position(), (75., 0.));
+        
+        obj.set_rotation(30.);
+        obj.mirror_horizontally(60.);
+        assert_eq!(obj.position(), (45., 0.));
+        assert_eq!(obj.flip(), (true, false));
+        assert_eq!(obj.rotation(), -30.);
+        
+        obj.rotate_around(15., 0., 90.);
+        assert_eq!(obj.position(), (15., -30.));
+        assert_eq!(obj.rotation(), 60.);
+        
+        let mut trigger = LevelObject::new(901, 0., 0.);
+        trigger.set_raw("28", "10".to_string()).unwrap();
+        trigger.rotate_around(0., 0., 90.);
+        assert_eq!((trigger.raw("28"), trigger.raw("29")), (Some("0"), Some("-10")));
+    }
 }
-
@@ -1,16 +1,138 @@
-use crate::models::object::Color;
+use crate::models::object::{is_trigger, Color};
 
-struct ObjectVariant {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Solid,
+    Hazard,
+    // orbs, pads, portals, coins and anything else the player interacts with
+    Special,
+    Trigger,
+    Decoration,
+}
+
+// the table only covers objects we've needed so far, anything else is None
+#[derive(Debug, Clone)]
+pub struct ObjectVariant {
+    kind: ObjectKind,
+    // sprite size before scaling and rotation
+    size: (f32, f32),
+    // None for objects the player goes straight through
+    hitbox: Option<(f32, f32)>,
+    // where the sprite and hitbox are centred, relative to the object's position
+    offset: (f32, f32),
+    // covers everything drawn under it
+    opaque: bool,
+    // the next few aren't read by anything yet
+    #[allow(dead_code)]
     default_col: Option<Color>,
     z_order: i32,
     force_bottom: bool,
+    #[allow(dead_code)]
     has_child: bool,
+    #[allow(dead_code)]
     has_color_child: bool,
+    #[allow(dead_code)]
     dont_show: bool,
 }
 
 impl ObjectVariant {
+    fn new(kind: ObjectKind) -> ObjectVariant {
+        ObjectVariant {
+            kind,
+            size: (30., 30.),
+            hitbox: None,
+            offset: (0., 0.),
+            opaque: false,
+            default_col: None,
+            z_order: 0,
+            force_bottom: false,
+            has_child: false,
+            has_color_child: false,
+            dont_show: kind == ObjectKind::Trigger,
+        }
+    }
+    
+    fn size(mut self, width: f32, height: f32) -> ObjectVariant {
+        self.size = (width, height);
+        self
+    }
+    
+    fn hitbox(mut self, width: f32, height: f32) -> ObjectVariant {
+        self.hitbox = Some((width, height));
+        self
+    }
+    
+    fn opaque(mut self) -> ObjectVariant {
+        self.opaque = true;
+        self
+    }
+    
     pub fn from_id(id: u16) -> Option<ObjectVariant> {
-        None  // placeholder
+        if is_trigger(id) {
+            return Some(ObjectVariant::new(ObjectKind::Trigger));
+        }
+        // hazard hitboxes are smaller than their sprites, these are close but not exact
+        let variant = match id {
+            // filled in blocks
+            1..=7 => ObjectVariant::new(ObjectKind::Solid).hitbox(30., 30.).opaque(),
+            40 => ObjectVariant::new(ObjectKind::Solid).size(30., 15.).hitbox(30., 15.).opaque(),
+            8 | 9 | 61 | 103 | 392 => ObjectVariant::new(ObjectKind::Hazard).hitbox(6., 12.),
+            39 => ObjectVariant::new(ObjectKind::Hazard).size(30., 15.).hitbox(6., 6.),
+            // sawblades
+            88 => ObjectVariant::new(ObjectKind::Hazard).size(90., 90.).hitbox(64., 64.),
+            89 => ObjectVariant::new(ObjectKind::Hazard).size(60., 60.).hitbox(43., 43.),
+            98 => ObjectVariant::new(ObjectKind::Hazard).size(36., 36.).hitbox(24., 24.),
+            // pads and orbs
+            35 | 67 | 140 | 1332 | 3005 => {
+                ObjectVariant::new(ObjectKind::Special).size(30., 8.).hitbox(25., 4.)
+            },
+            36 | 84 | 141 | 1022 | 1330 | 1333 | 1594 | 1704 | 1751 | 3004 | 3027 => {
+                ObjectVariant::new(ObjectKind::Special).size(36., 36.).hitbox(36., 36.)
+            },
+            // portals
+            10 | 11 | 12 | 13 | 45 | 46 | 47 | 99 | 101 | 111 | 286 | 287 | 660 | 745 | 747
+            | 749 | 1331 | 1933 => ObjectVariant::new(ObjectKind::Special).size(34., 86.).hitbox(25., 75.),
+            200..=203 | 1334 => ObjectVariant::new(ObjectKind::Special).size(35., 44.).hitbox(35., 44.),
+            // coins
+            142 | 1329 => ObjectVariant::new(ObjectKind::Special).hitbox(30., 30.),
+            31 => ObjectVariant { dont_show: true, ..ObjectVariant::new(ObjectKind::Special) },
+            18..=21 | 914 => ObjectVariant::new(ObjectKind::Decoration),
+            _ => { return None; },
+        };
+        Some(variant)
+    }
+    
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+    
+    pub fn sprite_size(&self) -> (f32, f32) {
+        self.size
+    }
+    
+    pub fn hitbox_size(&self) -> Option<(f32, f32)> {
+        self.hitbox
+    }
+    
+    pub fn offset(&self) -> (f32, f32) {
+        self.offset
+    }
+    
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+    
+    pub fn default_z_order(&self) -> i32 {
+        self.z_order
+    }
+    
+    // the layer objects without key 24 (or with 24 set to 0) are drawn on, B1 or T1
+    pub fn default_z_layer(&self) -> i8 {
+        if self.force_bottom { 3 } else { 5 }
+    }
+    
+    // whether the player can touch it, triggers don't count
+    pub fn has_gameplay(&self) -> bool {
+        matches!(self.kind, ObjectKind::Solid | ObjectKind::Hazard | ObjectKind::Special)
     }
 }
\ No newline at end of file
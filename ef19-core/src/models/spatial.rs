@@ -0,0 +1,354 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::models::level::ObjectList;
+use crate::models::object::{LevelObject, ObjectVariant};
+
+// objects we don't have in the variant table are treated as a full block
+const DEFAULT_SIZE: (f32, f32) = (30., 30.);
+// four blocks, most objects end up in one or two cells
+const DEFAULT_CELL_SIZE: f32 = 120.;
+// objects covering more cells than this go in their own list instead of the grid
+const MAX_OBJECT_CELLS: i64 = 256;
+// leeway for positions that went through a float round trip
+const EPSILON: f32 = 0.01;
+
+// axis aligned box in world units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl Rect {
+    pub fn new(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Rect {
+        Rect { min_x, min_y, max_x, max_y }
+    }
+    
+    pub fn from_center(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect::new(x - width / 2., y - height / 2., x + width / 2., y + height / 2.)
+    }
+    
+    pub fn width(&self) -> f32 {
+        self.max_x - self.min_x
+    }
+    
+    pub fn height(&self) -> f32 {
+        self.max_y - self.min_y
+    }
+    
+    pub fn center(&self) -> (f32, f32) {
+        ((self.min_x + self.max_x) / 2., (self.min_y + self.max_y) / 2.)
+    }
+    
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        self.min_x <= x + EPSILON && x <= self.max_x + EPSILON
+            && self.min_y <= y + EPSILON && y <= self.max_y + EPSILON
+    }
+    
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.contains_point(other.min_x, other.min_y) && self.contains_point(other.max_x, other.max_y)
+    }
+    
+    // boxes that only share an edge don't count, blocks next to each other aren't overlapping
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.min_x < other.max_x - EPSILON && other.min_x < self.max_x - EPSILON
+            && self.min_y < other.max_y - EPSILON && other.min_y < self.max_y - EPSILON
+    }
+    
+    // 0 for points inside the box
+    pub fn distance_to(&self, x: f32, y: f32) -> f32 {
+        let dx = (self.min_x - x).max(x - self.max_x).max(0.);
+        let dy = (self.min_y - y).max(y - self.max_y).max(0.);
+        dx.hypot(dy)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoundsKind {
+    // what gets drawn
+    #[default]
+    Sprite,
+    // what the player collides with, objects without one aren't indexed
+    Hitbox,
+}
+
+// box of the given size around the object after flip, scale and rotation are applied
+fn transformed_bounds(obj: &LevelObject, (width, height): (f32, f32), (offset_x, offset_y): (f32, f32)) -> Rect {
+    let (flip_x, flip_y) = obj.flip();
+    let (scale_x, scale_y) = obj.scale();
+    let (width, height) = (width * scale_x.abs(), height * scale_y.abs());
+    let offset_x = if flip_x { -offset_x } else { offset_x } * scale_x;
+    let offset_y = if flip_y { -offset_y } else { offset_y } * scale_y;
+    
+    // rotation goes clockwise
+    let (sin, cos) = obj.rotation().to_radians().sin_cos();
+    let center_x = obj.x_pos() + offset_x * cos + offset_y * sin;
+    let center_y = obj.y_pos() - offset_x * sin + offset_y * cos;
+    let rotated_w = width * cos.abs() + height * sin.abs();
+    let rotated_h = width * sin.abs() + height * cos.abs();
+    Rect::from_center(center_x, center_y, rotated_w, rotated_h)
+}
+
+impl LevelObject {
+    pub fn sprite_bounds(&self) -> Rect {
+        let variant = ObjectVariant::from_id(self.id());
+        let size = variant.as_ref().map_or(DEFAULT_SIZE, |v| v.sprite_size());
+        let offset = variant.as_ref().map_or((0., 0.), |v| v.offset());
+        transformed_bounds(self, size, offset)
+    }
+    
+    // None for decoration, triggers and anything not in the variant table
+    pub fn hitbox_bounds(&self) -> Option<Rect> {
+        let variant = ObjectVariant::from_id(self.id())?;
+        Some(transformed_bounds(self, variant.hitbox_size()?, variant.offset()))
+    }
+    
+    pub fn bounds(&self, kind: BoundsKind) -> Option<Rect> {
+        match kind {
+            BoundsKind::Sprite => Some(self.sprite_bounds()),
+            BoundsKind::Hitbox => self.hitbox_bounds(),
+        }
+    }
+}
+
+// grid of buckets over an object list, by index into the list it was built from
+#[derive(Debug)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    bounds: Vec<Option<Rect>>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    // hugely scaled objects, checked by every query
+    oversized: Vec<usize>,
+    // range of cells anything in the grid is in, so searches know when to stop
+    extent: Option<((i32, i32), (i32, i32))>,
+}
+
+impl SpatialIndex {
+    pub fn new(objects: &ObjectList, kind: BoundsKind) -> SpatialIndex {
+        SpatialIndex::with_cell_size(objects, kind, DEFAULT_CELL_SIZE)
+    }
+    
+    pub fn with_cell_size(objects: &ObjectList, kind: BoundsKind, cell_size: f32) -> SpatialIndex {
+        let mut index = SpatialIndex {
+            cell_size,
+            bounds: objects.objects().iter().map(|obj| obj.bounds(kind)).collect(),
+            cells: HashMap::new(),
+            oversized: Vec::new(),
+            extent: None,
+        };
+        for (i, rect) in index.bounds.iter().enumerate() {
+            let Some(rect) = rect else { continue; };
+            let (min, max) = index.cell_range(rect);
+            let cells = (max.0 as i64 - min.0 as i64 + 1) * (max.1 as i64 - min.1 as i64 + 1);
+            if cells > MAX_OBJECT_CELLS {
+                index.oversized.push(i);
+                continue;
+            }
+            for cx in min.0..=max.0 {
+                for cy in min.1..=max.1 {
+                    index.cells.entry((cx, cy)).or_default().push(i);
+                }
+            }
+            index.extent = Some(match index.extent {
+                Some((lo, hi)) => ((lo.0.min(min.0), lo.1.min(min.1)), (hi.0.max(max.0), hi.1.max(max.1))),
+                None => (min, max),
+            });
+        }
+        index
+    }
+    
+    fn cell(&self, x: f32, y: f32) -> (i32, i32) {
+        ((x / self.cell_size).floor() as i32, (y / self.cell_size).floor() as i32)
+    }
+    
+    fn cell_range(&self, rect: &Rect) -> ((i32, i32), (i32, i32)) {
+        (self.cell(rect.min_x, rect.min_y), self.cell(rect.max_x, rect.max_y))
+    }
+    
+    // every object indexed in the cells a box touches, may include ones that don't overlap it
+    fn candidates(&self, rect: &Rect) -> BTreeSet<usize> {
+        let (min, max) = self.cell_range(rect);
+        let mut found = BTreeSet::new();
+        for cx in min.0..=max.0 {
+            for cy in min.1..=max.1 {
+                if let Some(cell) = self.cells.get(&(cx, cy)) {
+                    found.extend(cell);
+                }
+            }
+        }
+        found.extend(&self.oversized);
+        found
+    }
+    
+    pub fn bounds(&self, i: usize) -> Option<Rect> {
+        self.bounds.get(i).copied().flatten()
+    }
+    
+    // objects overlapping the box, in list order
+    pub fn query(&self, rect: &Rect) -> Vec<usize> {
+        self.candidates(rect).into_iter()
+            .filter(|&i| self.bounds[i].is_some_and(|b| b.overlaps(rect)))
+            .collect()
+    }
+    
+    // objects completely inside the box, in list order
+    pub fn query_within(&self, rect: &Rect) -> Vec<usize> {
+        self.candidates(rect).into_iter()
+            .filter(|&i| self.bounds[i].is_some_and(|b| rect.contains(&b)))
+            .collect()
+    }
+    
+    pub fn query_point(&self, x: f32, y: f32) -> Vec<usize> {
+        let mut found: Vec<usize> = self.cells.get(&self.cell(x, y)).map_or(Vec::new(), |cell| cell.clone());
+        found.extend(&self.oversized);
+        found.retain(|&i| self.bounds[i].is_some_and(|b| b.contains_point(x, y)));
+        found.sort_unstable();
+        found
+    }
+    
+    // closest object by distance to its box, the earliest in the list wins ties
+    pub fn nearest(&self, x: f32, y: f32) -> Option<usize> {
+        let mut best: Option<(f32, usize)> = None;
+        let consider = |best: &mut Option<(f32, usize)>, i: usize| {
+            let distance = self.bounds[i].unwrap().distance_to(x, y);
+            if best.is_none_or(|(d, j)| distance < d || (distance == d && i < j)) {
+                *best = Some((distance, i));
+            }
+        };
+        self.oversized.iter().for_each(|&i| consider(&mut best, i));
+        
+        if let Some((lo, hi)) = self.extent {
+            // cells can sit at the ends of i32 for far out objects, so the ring maths is done wider
+            let (cx, cy) = self.cell(x, y);
+            let (cx, cy) = (cx as i64, cy as i64);
+            let (lo, hi) = ((lo.0 as i64, lo.1 as i64), (hi.0 as i64, hi.1 as i64));
+            // past this ring every cell is outside the extent
+            let max_ring = [cx - lo.0, hi.0 - cx, cy - lo.1, hi.1 - cy].into_iter().max().unwrap().max(0);
+            // and before this one, when the point is outside it
+            let min_ring = [lo.0 - cx, cx - hi.0, lo.1 - cy, cy - hi.1].into_iter().max().unwrap().max(0);
+            
+            for ring in min_ring..=max_ring {
+                // far apart objects make for huge rings, past a point it's quicker to look at every cell
+                if ring * 8 > self.cells.len() as i64 {
+                    self.cells.values().flatten().for_each(|&i| consider(&mut best, i));
+                    break;
+                }
+                // only the edge of the ring, the inside has been done already
+                let edge = (-ring..=ring).flat_map(|d| [(d, -ring), (d, ring), (-ring, d), (ring, d)]);
+                let mut seen = BTreeSet::new();
+                for (dx, dy) in edge.filter(|&c| seen.insert(c)) {
+                    let (Ok(x), Ok(y)) = (i32::try_from(cx + dx), i32::try_from(cy + dy)) else { continue; };
+                    let Some(cell) = self.cells.get(&(x, y)) else { continue; };
+                    cell.iter().for_each(|&i| consider(&mut best, i));
+                }
+                // anything not found yet is at least a ring's width away
+                if best.is_some_and(|(d, _)| d <= ring as f32 * self.cell_size) {
+                    break;
+                }
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+    
+    // other objects overlapping this one, in list order
+    pub fn overlapping(&self, i: usize) -> Vec<usize> {
+        let Some(rect) = self.bounds(i) else { return Vec::new(); };
+        let mut found = self.query(&rect);
+        found.retain(|&j| j != i);
+        found
+    }
+    
+    // every overlapping pair, lower index first
+    pub fn overlaps(&self) -> Vec<(usize, usize)> {
+        let mut pairs = BTreeSet::new();
+        for cell in self.cells.values() {
+            for (n, &i) in cell.iter().enumerate() {
+                for &j in &cell[n + 1..] {
+                    if self.bounds[i].unwrap().overlaps(&self.bounds[j].unwrap()) {
+                        pairs.insert((i.min(j), i.max(j)));
+                    }
+                }
+            }
+        }
+        for &i in &self.oversized {
+            for j in self.query(&self.bounds[i].unwrap()) {
+                if j != i {
+                    pairs.insert((i.min(j), i.max(j)));
+                }
+            }
+        }
+        pairs.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn object(raw: &str) -> LevelObject {
+        let list = ObjectList::from_raw_str(&format!("kA2,0;{};", raw)).unwrap();
+        list.objects()[0].clone()
+    }
+    
+    #[test]
+    fn object_bounds() {
+        assert_eq!(object("1,1,2,15,3,15").sprite_bounds(), Rect::new(0., 0., 30., 30.));
+        // slabs lie flat, stand up when rotated
+        assert_eq!(object("1,40,2,15,3,7.5").sprite_bounds(), Rect::new(0., 0., 30., 15.));
+        let rotated = object("1,40,2,15,3,15,6,90").sprite_bounds();
+        assert!((rotated.width() - 15.).abs() < EPSILON && (rotated.height() - 30.).abs() < EPSILON);
+        // scaled saws
+        assert_eq!(object("1,89,2,0,3,0,32,2").sprite_bounds(), Rect::new(-60., -60., 60., 60.));
+        assert_eq!(object("1,8,2,15,3,15").hitbox_bounds(), Some(Rect::new(12., 9., 18., 21.)));
+        assert_eq!(object("1,18,2,15,3,15").hitbox_bounds(), None);
+    }
+    
+    #[test]
+    fn offset_follows_flip_and_rotation() {
+        let bounds = |raw| transformed_bounds(&object(raw), (10., 10.), (10., 0.)).center();
+        let close = |(x, y): (f32, f32), (ex, ey): (f32, f32)| (x - ex).abs() < EPSILON && (y - ey).abs() < EPSILON;
+        assert!(close(bounds("1,1,2,0,3,0"), (10., 0.)));
+        assert!(close(bounds("1,1,2,0,3,0,4,1"), (-10., 0.)));
+        assert!(close(bounds("1,1,2,0,3,0,6,90"), (0., -10.)));
+        assert!(close(bounds("1,1,2,0,3,0,32,2"), (20., 0.)));
+    }
+    
+    #[test]
+    fn queries() {
+        let objects = ObjectList::from_raw_str(concat!(
+            "kA2,0;",
+            "1,1,2,15,3,15;",
+            "1,1,2,45,3,15;",
+            "1,18,2,30,3,15;",
+            "1,1,2,1005,3,15;",
+            "1,88,2,2000,3,500;",
+        )).unwrap();
+        let index = SpatialIndex::new(&objects, BoundsKind::Sprite);
+        
+        assert_eq!(index.query(&Rect::new(20., 0., 40., 30.)), vec![0, 1, 2]);
+        assert_eq!(index.query_within(&Rect::new(0., 0., 60., 30.)), vec![0, 1, 2]);
+        assert_eq!(index.query_point(1000., 10.), vec![3]);
+        // blocks next to each other don't overlap, the decoration sat across both does
+        assert_eq!(index.overlaps(), vec![(0, 2), (1, 2)]);
+        assert_eq!(index.overlapping(2), vec![0, 1]);
+        
+        assert_eq!(index.nearest(700., 15.), Some(3));
+        assert_eq!(index.nearest(-5000., 0.), Some(0));
+        assert_eq!(index.nearest(2100., 700.), Some(4));
+        
+        // a huge block isn't spread over the grid but still shows up everywhere it covers
+        let huge = ObjectList::from_raw_str("kA2,0;1,1,2,0,3,0,32,1000;1,1,2,5000,3,15;1,1,2,1e30,3,-1e30;").unwrap();
+        let huge_index = SpatialIndex::new(&huge, BoundsKind::Sprite);
+        assert_eq!(huge_index.oversized, vec![0]);
+        assert!(huge_index.cells.len() < 10);
+        assert_eq!(huge_index.query_point(-10000., 10000.), vec![0]);
+        assert_eq!(huge_index.overlaps(), vec![(0, 1)]);
+        assert_eq!(huge_index.nearest(1e30, 1e30), Some(0));
+        assert_eq!(huge_index.nearest(-3e38, -3e38), Some(0));
+        
+        let hitboxes = SpatialIndex::new(&objects, BoundsKind::Hitbox);
+        assert_eq!(hitboxes.bounds(2), None);
+        assert!(hitboxes.overlaps().is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+use crate::models::level::ObjectList;
+use crate::models::object::LevelObject;
+
+// kA4 in the header and start positions
+const SPEED_KEY: &str = "kA4";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speed {
+    Slow,  // 0.5x
+    Normal,  // 1x
+    Fast,  // 2x
+    Faster,  // 3x
+    Fastest,  // 4x
+}
+impl Speed {
+    pub fn from_portal_id(id: u16) -> Option<Speed> {
+        match id {
+            200 => Some(Speed::Slow),
+            201 => Some(Speed::Normal),
+            202 => Some(Speed::Fast),
+            203 => Some(Speed::Faster),
+            1334 => Some(Speed::Fastest),
+            _ => None,
+        }
+    }
+    pub fn portal_id(self) -> u16 {
+        match self {
+            Speed::Slow => 200,
+            Speed::Normal => 201,
+            Speed::Fast => 202,
+            Speed::Faster => 203,
+            Speed::Fastest => 1334,
+        }
+    }
+    // value used by kA4, which isn't in the same order as the portals
+    pub fn from_setting(value: u8) -> Option<Speed> {
+        match value {
+            0 => Some(Speed::Normal),
+            1 => Some(Speed::Slow),
+            2 => Some(Speed::Fast),
+            3 => Some(Speed::Faster),
+            4 => Some(Speed::Fastest),
+            _ => None,
+        }
+    }
+    pub fn setting(self) -> u8 {
+        match self {
+            Speed::Normal => 0,
+            Speed::Slow => 1,
+            Speed::Fast => 2,
+            Speed::Faster => 3,
+            Speed::Fastest => 4,
+        }
+    }
+}
+
+// player speed in units per second (30 units to a block)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedTable {
+    pub slow: f32,
+    pub normal: f32,
+    pub fast: f32,
+    pub faster: f32,
+    pub fastest: Option<f32>,
+}
+impl SpeedTable {
+    // 4x doesn't exist in 1.9, where it'd end up as 3x
+    pub fn speed(&self, speed: Speed) -> f32 {
+        match speed {
+            Speed::Slow => self.slow,
+            Speed::Normal => self.normal,
+            Speed::Fast => self.fast,
+            Speed::Faster => self.faster,
+            Speed::Fastest => self.fastest.unwrap_or(self.faster),
+        }
+    }
+}
+
+pub const SPEEDS_19: SpeedTable = SpeedTable {
+    slow: 251.16,
+    normal: 311.58,
+    fast: 387.42,
+    faster: 468.,
+    fastest: None,
+};
+
+pub const SPEEDS_22: SpeedTable = SpeedTable {
+    slow: 251.16,
+    normal: 311.58,
+    fast: 387.42,
+    faster: 468.,
+    fastest: Some(576.),
+};
+
+// length category shown on the level page, stored as its discriminant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelLength {
+    Tiny = 0,
+    Short = 1,
+    Medium = 2,
+    Long = 3,
+    ExtraLong = 4,
+}
+impl LevelLength {
+    pub fn from_seconds(seconds: f32) -> LevelLength {
+        match seconds {
+            s if s < 10. => LevelLength::Tiny,
+            s if s < 30. => LevelLength::Short,
+            s if s < 60. => LevelLength::Medium,
+            s if s < 120. => LevelLength::Long,
+            _ => LevelLength::ExtraLong,
+        }
+    }
+}
+
+// x_end is infinite for the last segment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub x_start: f32,
+    pub x_end: f32,
+    pub speed: Speed,
+    // time at which the player reaches x_start
+    pub seconds_start: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    segments: Vec<Segment>,
+    table: SpeedTable,
+}
+
+impl Timeline {
+    // timeline when playing from the start of the level
+    pub fn new(objects: &ObjectList, table: SpeedTable) -> Timeline {
+        let speed = objects.header_value(SPEED_KEY)
+            .and_then(|v| v.parse().ok())
+            .and_then(Speed::from_setting)
+            .unwrap_or(Speed::Normal);
+        Self::from_start(objects, table, 0., speed)
+    }
+    
+    // timeline when playing from a start position, portals behind it don't count
+    pub fn from_start_pos(objects: &ObjectList, table: SpeedTable, start_pos: &LevelObject) -> Timeline {
+        let speed = start_pos.raw(SPEED_KEY)
+            .and_then(|v| v.parse().ok())
+            .and_then(Speed::from_setting)
+            .unwrap_or(Speed::Normal);
+        Self::from_start(objects, table, start_pos.x_pos(), speed)
+    }
+    
+    fn from_start(objects: &ObjectList, table: SpeedTable, x_start: f32, speed: Speed) -> Timeline {
+        let mut portals: Vec<(f32, Speed)> = objects.objects().iter()
+            .filter(|obj| obj.x_pos() >= x_start)
+            .filter_map(|obj| Some((obj.x_pos(), Speed::from_portal_id(obj.id())?)))
+            .collect();
+        portals.sort_by(|a, b| a.0.total_cmp(&b.0));
+        
+        let mut segments = vec![Segment {
+            x_start,
+            x_end: f32::INFINITY,
+            speed,
+            seconds_start: 0.,
+        }];
+        for (x, speed) in portals {
+            let last = segments.last_mut().unwrap();
+            last.x_end = x;
+            let seconds_start = last.seconds_start + (x - last.x_start) / table.speed(last.speed);
+            segments.push(Segment {
+                x_start: x,
+                x_end: f32::INFINITY,
+                speed,
+                seconds_start,
+            });
+        }
+        
+        Timeline { segments, table }
+    }
+    
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+    
+    pub fn table(&self) -> &SpeedTable {
+        &self.table
+    }
+    
+    // positions before the start belong to the first segment
+    pub fn segment_at(&self, x: f32) -> &Segment {
+        self.segments.iter()
+            .rev()
+            .find(|seg| seg.x_start <= x)
+            .unwrap_or(&self.segments[0])
+    }
+    
+    pub fn speed_at(&self, x: f32) -> Speed {
+        self.segment_at(x).speed
+    }
+    
+    pub fn x_to_seconds(&self, x: f32) -> f32 {
+        let seg = self.segment_at(x);
+        seg.seconds_start + (x - seg.x_start) / self.table.speed(seg.speed)
+    }
+    
+    // how long it takes to reach the furthest object
+    pub fn length(&self, objects: &ObjectList) -> LevelLength {
+        let level_end = objects.objects().iter().map(|obj| obj.x_pos()).fold(0., f32::max);
+        LevelLength::from_seconds(self.x_to_seconds(level_end))
+    }
+    
+    pub fn seconds_to_x(&self, seconds: f32) -> f32 {
+        let seg = self.segments.iter()
+            .rev()
+            .find(|seg| seg.seconds_start <= seconds)
+            .unwrap_or(&self.segments[0]);
+        seg.x_start + (seconds - seg.seconds_start) * self.table.speed(seg.speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.001, "{a} != {b}");
+    }
+    
+    #[test]
+    fn header_speed_and_portals() {
+        let objects = ObjectList::from_raw_str(
+            "kA4,3;1,1,2,100,3,15;1,201,2,936,3,15;1,1334,2,1247.58,3,15;"
+        ).unwrap();
+        let timeline = Timeline::new(&objects, SPEEDS_22);
+        
+        assert_eq!(timeline.segments().len(), 3);
+        assert_eq!(timeline.speed_at(500.), Speed::Faster);
+        assert_eq!(timeline.speed_at(1000.), Speed::Normal);
+        assert_eq!(timeline.speed_at(2000.), Speed::Fastest);
+        
+        assert_close(timeline.x_to_seconds(936.), 2.);
+        assert_close(timeline.x_to_seconds(1247.58), 3.);
+        assert_close(timeline.x_to_seconds(1823.58), 4.);
+        assert_close(timeline.seconds_to_x(3.5), 1535.58);
+        assert_close(timeline.seconds_to_x(1.), 468.);
+    }
+    
+    #[test]
+    fn speed_tables() {
+        let objects = ObjectList::from_raw_str("kA4,4;1,1,2,100,3,15;").unwrap();
+        assert_close(Timeline::new(&objects, SPEEDS_22).x_to_seconds(576.), 1.);
+        assert_close(Timeline::new(&objects, SPEEDS_19).x_to_seconds(468.), 1.);
+    }
+    
+    #[test]
+    fn length() {
+        let objects = ObjectList::from_raw_str("kA4,0;1,1,2,6231.6,3,15;").unwrap();
+        assert_eq!(Timeline::new(&objects, SPEEDS_19).length(&objects), LevelLength::Short);
+        let objects = ObjectList::from_raw_str("kA4,0;1,1,2,40000,3,15;").unwrap();
+        assert_eq!(Timeline::new(&objects, SPEEDS_19).length(&objects), LevelLength::ExtraLong);
+        assert_eq!(LevelLength::from_seconds(9.9), LevelLength::Tiny);
+        assert_eq!(LevelLength::from_seconds(60.), LevelLength::Long);
+    }
+    
+    #[test]
+    fn start_pos() {
+        let objects = ObjectList::from_raw_str(
+            "kA4,0;1,202,2,100,3,15;1,31,2,300,3,15,kA4,1;1,203,2,551.16,3,15;"
+        ).unwrap();
+        let timeline = Timeline::from_start_pos(&objects, SPEEDS_22, &objects.objects()[1]);
+        
+        // the 2x portal is behind the start pos
+        assert_eq!(timeline.segments().len(), 2);
+        assert_eq!(timeline.speed_at(400.), Speed::Slow);
+        assert_close(timeline.x_to_seconds(551.16), 1.);
+        assert_close(timeline.seconds_to_x(2.), 1019.16);
+    }
+}
@@ -0,0 +1,496 @@
+use std::str::FromStr;
+use crate::codec::format::GdFormat;
+use crate::models::object::LevelObject;
+
+pub const COLOR_TRIGGER_ID: u16 = 899;
+pub const MOVE_TRIGGER_ID: u16 = 901;
+pub const PULSE_TRIGGER_ID: u16 = 1006;
+pub const ALPHA_TRIGGER_ID: u16 = 1007;
+pub const TOGGLE_TRIGGER_ID: u16 = 1049;
+pub const SPAWN_TRIGGER_ID: u16 = 1268;
+pub const ROTATE_TRIGGER_ID: u16 = 1346;
+pub const FOLLOW_TRIGGER_ID: u16 = 1347;
+pub const SHAKE_TRIGGER_ID: u16 = 1520;
+
+// colour triggers from before 899, each one only does a single channel
+const LEGACY_COLOR_TRIGGERS: [(u16, u16); 9] = [
+    (29, 1000),  // background
+    (30, 1001),  // ground
+    (104, 1002),  // line
+    (105, 1004),  // object
+    (221, 1),
+    (717, 2),
+    (718, 3),
+    (743, 4),
+    (744, 1003),  // 3d line
+];
+
+fn legacy_channel(id: u16) -> Option<u16> {
+    LEGACY_COLOR_TRIGGERS.iter().find(|(legacy, _)| *legacy == id).map(|(_, channel)| *channel)
+}
+
+fn get<T: FromStr>(obj: &LevelObject, key: &str) -> Option<T> {
+    obj.raw(key).and_then(|v| v.parse().ok())
+}
+
+fn get_or<T: FromStr>(obj: &LevelObject, key: &str, default: T) -> T {
+    get(obj, key).unwrap_or(default)
+}
+
+fn flag(obj: &LevelObject, key: &str) -> bool {
+    obj.raw(key) == Some("1")
+}
+
+// 0 means unset for every id a trigger refers to
+fn id_key(obj: &LevelObject, key: &str) -> Option<u16> {
+    get(obj, key).filter(|&v| v != 0)
+}
+
+// values that are already the default are left out, like the game does
+fn set<T: GdFormat + PartialEq>(obj: &mut LevelObject, key: &str, val: T, default: T) {
+    if val == default {
+        obj.remove_raw(key);
+    } else {
+        obj.set_other(key, val.gd_format());
+    }
+}
+
+fn set_id_key(obj: &mut LevelObject, key: &str, val: Option<u16>) {
+    set(obj, key, val.unwrap_or(0), 0);
+}
+
+// how a trigger gets fired, shared by every kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Activation {
+    pub touch_triggered: bool,  // 11
+    pub spawn_triggered: bool,  // 62
+    pub multi_trigger: bool,  // 87
+}
+
+impl Activation {
+    fn read(obj: &LevelObject) -> Activation {
+        Activation {
+            touch_triggered: flag(obj, "11"),
+            spawn_triggered: flag(obj, "62"),
+            multi_trigger: flag(obj, "87"),
+        }
+    }
+    
+    fn write(&self, obj: &mut LevelObject) {
+        set(obj, "11", self.touch_triggered, false);
+        set(obj, "62", self.spawn_triggered, false);
+        set(obj, "87", self.multi_trigger, false);
+    }
+    
+    // fires when the player passes it, rather than from a spawn trigger or touch
+    pub fn fires_on_pass(&self) -> bool {
+        !self.touch_triggered && !self.spawn_triggered
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorTrigger {
+    // channel id, 1000 and up are the special channels
+    pub channel: u16,  // 23, or implied by the legacy trigger id
+    pub rgb: (u8, u8, u8),  // 7, 8, 9
+    pub duration: f32,  // 10
+    pub opacity: f32,  // 35
+    pub blending: bool,  // 17
+    pub tint_ground: bool,  // 14
+    pub player_color: Option<u8>,  // 15 for player 1, 16 for player 2
+    pub copy_channel: Option<u16>,  // 50
+    pub copy_hsv: Option<String>,  // 49
+    // which of the legacy ids it was loaded from, None for 899
+    pub legacy_id: Option<u16>,
+}
+
+impl ColorTrigger {
+    // the pre-899 trigger that does the same thing, if there is one
+    pub fn legacy_equivalent(&self) -> Option<u16> {
+        LEGACY_COLOR_TRIGGERS.iter().find(|(_, channel)| *channel == self.channel).map(|(id, _)| *id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveTrigger {
+    pub target_group: Option<u16>,  // 51
+    // in move units, a third of a position unit
+    pub offset: (f32, f32),  // 28, 29
+    pub duration: f32,  // 10
+    pub easing: u8,  // 30
+    pub easing_rate: f32,  // 85
+    pub lock_to_player: (bool, bool),  // 58, 59
+    // moves to this group instead of by the offset
+    pub use_target: bool,  // 100
+    pub target_pos_group: Option<u16>,  // 71
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PulseTrigger {
+    // a colour channel or a group, depending on target_is_group
+    pub target: Option<u16>,  // 51
+    pub target_is_group: bool,  // 52
+    pub rgb: (u8, u8, u8),  // 7, 8, 9
+    pub fade_in: f32,  // 45
+    pub hold: f32,  // 46
+    pub fade_out: f32,  // 47
+    pub use_hsv: bool,  // 48
+    pub hsv: Option<String>,  // 49
+    pub copy_channel: Option<u16>,  // 50
+    pub exclusive: bool,  // 86
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlphaTrigger {
+    pub target_group: Option<u16>,  // 51
+    pub duration: f32,  // 10
+    pub opacity: f32,  // 35
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToggleTrigger {
+    pub target_group: Option<u16>,  // 51
+    // unticked means toggle off
+    pub activate: bool,  // 56
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnTrigger {
+    pub target_group: Option<u16>,  // 51
+    pub delay: f32,  // 63
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotateTrigger {
+    pub target_group: Option<u16>,  // 51
+    pub center_group: Option<u16>,  // 71
+    pub degrees: f32,  // 68
+    pub times_360: i32,  // 69
+    pub duration: f32,  // 10
+    pub easing: u8,  // 30
+    pub easing_rate: f32,  // 85
+    pub lock_rotation: bool,  // 70
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowTrigger {
+    pub target_group: Option<u16>,  // 51
+    pub follow_group: Option<u16>,  // 71
+    pub modifier: (f32, f32),  // 72, 73
+    pub duration: f32,  // 10
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShakeTrigger {
+    pub strength: f32,  // 75
+    pub interval: f32,  // 84
+    pub duration: f32,  // 10
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerKind {
+    Color(ColorTrigger),
+    Move(MoveTrigger),
+    Pulse(PulseTrigger),
+    Alpha(AlphaTrigger),
+    Toggle(ToggleTrigger),
+    Spawn(SpawnTrigger),
+    Rotate(RotateTrigger),
+    Follow(FollowTrigger),
+    Shake(ShakeTrigger),
+}
+
+// the parameters of a trigger object, position and groups stay on the object
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+    pub activation: Activation,
+    pub kind: TriggerKind,
+}
+
+impl Trigger {
+    // None for anything that isn't one of the triggers above
+    pub fn from_object(obj: &LevelObject) -> Option<Trigger> {
+        let rgb = |obj: &LevelObject| (get_or(obj, "7", 255), get_or(obj, "8", 255), get_or(obj, "9", 255));
+        let kind = match obj.id() {
+            COLOR_TRIGGER_ID => TriggerKind::Color(ColorTrigger {
+                channel: get_or(obj, "23", 1),
+                rgb: rgb(obj),
+                duration: get_or(obj, "10", 0.5),
+                opacity: get_or(obj, "35", 1.),
+                blending: flag(obj, "17"),
+                tint_ground: flag(obj, "14"),
+                player_color: if flag(obj, "15") { Some(1) } else if flag(obj, "16") { Some(2) } else { None },
+                copy_channel: id_key(obj, "50"),
+                copy_hsv: obj.raw("49").map(|v| v.to_string()),
+                legacy_id: None,
+            }),
+            id if legacy_channel(id).is_some() => {
+                let channel = legacy_channel(id).unwrap();
+                TriggerKind::Color(ColorTrigger {
+                    channel,
+                    rgb: rgb(obj),
+                    duration: get_or(obj, "10", 0.5),
+                    opacity: 1.,
+                    blending: flag(obj, "17"),
+                    tint_ground: flag(obj, "14"),
+                    player_color: if flag(obj, "15") { Some(1) } else if flag(obj, "16") { Some(2) } else { None },
+                    copy_channel: None,
+                    copy_hsv: None,
+                    legacy_id: Some(id),
+                })
+            },
+            MOVE_TRIGGER_ID => TriggerKind::Move(MoveTrigger {
+                target_group: id_key(obj, "51"),
+                offset: (get_or(obj, "28", 0.), get_or(obj, "29", 0.)),
+                duration: get_or(obj, "10", 0.5),
+                easing: get_or(obj, "30", 0),
+                easing_rate: get_or(obj, "85", 2.),
+                lock_to_player: (flag(obj, "58"), flag(obj, "59")),
+                use_target: flag(obj, "100"),
+                target_pos_group: id_key(obj, "71"),
+            }),
+            PULSE_TRIGGER_ID => TriggerKind::Pulse(PulseTrigger {
+                target: id_key(obj, "51"),
+                target_is_group: flag(obj, "52"),
+                rgb: rgb(obj),
+                fade_in: get_or(obj, "45", 0.),
+                hold: get_or(obj, "46", 0.),
+                fade_out: get_or(obj, "47", 0.),
+                use_hsv: flag(obj, "48"),
+                hsv: obj.raw("49").map(|v| v.to_string()),
+                copy_channel: id_key(obj, "50"),
+                exclusive: flag(obj, "86"),
+            }),
+            ALPHA_TRIGGER_ID => TriggerKind::Alpha(AlphaTrigger {
+                target_group: id_key(obj, "51"),
+                duration: get_or(obj, "10", 0.5),
+                opacity: get_or(obj, "35", 1.),
+            }),
+            TOGGLE_TRIGGER_ID => TriggerKind::Toggle(ToggleTrigger {
+                target_group: id_key(obj, "51"),
+                activate: flag(obj, "56"),
+            }),
+            SPAWN_TRIGGER_ID => TriggerKind::Spawn(SpawnTrigger {
+                target_group: id_key(obj, "51"),
+                delay: get_or(obj, "63", 0.),
+            }),
+            ROTATE_TRIGGER_ID => TriggerKind::Rotate(RotateTrigger {
+                target_group: id_key(obj, "51"),
+                center_group: id_key(obj, "71"),
+                degrees: get_or(obj, "68", 0.),
+                times_360: get_or(obj, "69", 0),
+                duration: get_or(obj, "10", 0.5),
+                easing: get_or(obj, "30", 0),
+                easing_rate: get_or(obj, "85", 2.),
+                lock_rotation: flag(obj, "70"),
+            }),
+            FOLLOW_TRIGGER_ID => TriggerKind::Follow(FollowTrigger {
+                target_group: id_key(obj, "51"),
+                follow_group: id_key(obj, "71"),
+                modifier: (get_or(obj, "72", 1.), get_or(obj, "73", 1.)),
+                duration: get_or(obj, "10", 0.5),
+            }),
+            SHAKE_TRIGGER_ID => TriggerKind::Shake(ShakeTrigger {
+                strength: get_or(obj, "75", 0.),
+                interval: get_or(obj, "84", 0.),
+                duration: get_or(obj, "10", 0.5),
+            }),
+            _ => { return None; },
+        };
+        Some(Trigger { activation: Activation::read(obj), kind })
+    }
+    
+    pub fn id(&self) -> u16 {
+        match &self.kind {
+            TriggerKind::Color(t) => t.legacy_id.unwrap_or(COLOR_TRIGGER_ID),
+            TriggerKind::Move(_) => MOVE_TRIGGER_ID,
+            TriggerKind::Pulse(_) => PULSE_TRIGGER_ID,
+            TriggerKind::Alpha(_) => ALPHA_TRIGGER_ID,
+            TriggerKind::Toggle(_) => TOGGLE_TRIGGER_ID,
+            TriggerKind::Spawn(_) => SPAWN_TRIGGER_ID,
+            TriggerKind::Rotate(_) => ROTATE_TRIGGER_ID,
+            TriggerKind::Follow(_) => FOLLOW_TRIGGER_ID,
+            TriggerKind::Shake(_) => SHAKE_TRIGGER_ID,
+        }
+    }
+    
+    // the group it acts on, pulse triggers only count when they target a group
+    pub fn target_group(&self) -> Option<u16> {
+        match &self.kind {
+            TriggerKind::Move(t) => t.target_group,
+            TriggerKind::Pulse(t) => t.target.filter(|_| t.target_is_group),
+            TriggerKind::Alpha(t) => t.target_group,
+            TriggerKind::Toggle(t) => t.target_group,
+            TriggerKind::Spawn(t) => t.target_group,
+            TriggerKind::Rotate(t) => t.target_group,
+            TriggerKind::Follow(t) => t.target_group,
+            TriggerKind::Color(_) | TriggerKind::Shake(_) => None,
+        }
+    }
+    
+    // how long it keeps acting after it fires, in seconds
+    pub fn duration(&self) -> f32 {
+        match &self.kind {
+            TriggerKind::Color(t) => t.duration,
+            TriggerKind::Move(t) => t.duration,
+            TriggerKind::Pulse(t) => t.fade_in + t.hold + t.fade_out,
+            TriggerKind::Alpha(t) => t.duration,
+            TriggerKind::Rotate(t) => t.duration,
+            TriggerKind::Follow(t) => t.duration,
+            TriggerKind::Shake(t) => t.duration,
+            TriggerKind::Toggle(_) | TriggerKind::Spawn(_) => 0.,
+        }
+    }
+    
+    // writes the parameters back, the id is set too so a trigger can change kind
+    pub fn write_to(&self, obj: &mut LevelObject) {
+        obj.set_id(self.id());
+        self.activation.write(obj);
+        let set_rgb = |obj: &mut LevelObject, (r, g, b): (u8, u8, u8)| {
+            set(obj, "7", r, 255);
+            set(obj, "8", g, 255);
+            set(obj, "9", b, 255);
+        };
+        let set_string = |obj: &mut LevelObject, key: &str, val: &Option<String>| match val {
+            Some(v) => obj.set_other(key, v.clone()),
+            None => { obj.remove_raw(key); },
+        };
+        match &self.kind {
+            TriggerKind::Color(t) => {
+                if t.legacy_id.is_none() {
+                    set(obj, "23", t.channel, 1);
+                    set(obj, "35", t.opacity, 1.);
+                    set_id_key(obj, "50", t.copy_channel);
+                    set_string(obj, "49", &t.copy_hsv);
+                }
+                set_rgb(obj, t.rgb);
+                set(obj, "10", t.duration, 0.5);
+                set(obj, "17", t.blending, false);
+                set(obj, "14", t.tint_ground, false);
+                set(obj, "15", t.player_color == Some(1), false);
+                set(obj, "16", t.player_color == Some(2), false);
+            },
+            TriggerKind::Move(t) => {
+                set_id_key(obj, "51", t.target_group);
+                set(obj, "28", t.offset.0, 0.);
+                set(obj, "29", t.offset.1, 0.);
+                set(obj, "10", t.duration, 0.5);
+                set(obj, "30", t.easing, 0);
+                set(obj, "85", t.easing_rate, 2.);
+                set(obj, "58", t.lock_to_player.0, false);
+                set(obj, "59", t.lock_to_player.1, false);
+                set(obj, "100", t.use_target, false);
+                set_id_key(obj, "71", t.target_pos_group);
+            },
+            TriggerKind::Pulse(t) => {
+                set_id_key(obj, "51", t.target);
+                set(obj, "52", t.target_is_group, false);
+                set_rgb(obj, t.rgb);
+                set(obj, "45", t.fade_in, 0.);
+                set(obj, "46", t.hold, 0.);
+                set(obj, "47", t.fade_out, 0.);
+                set(obj, "48", t.use_hsv, false);
+                set_string(obj, "49", &t.hsv);
+                set_id_key(obj, "50", t.copy_channel);
+                set(obj, "86", t.exclusive, false);
+            },
+            TriggerKind::Alpha(t) => {
+                set_id_key(obj, "51", t.target_group);
+                set(obj, "10", t.duration, 0.5);
+                set(obj, "35", t.opacity, 1.);
+            },
+            TriggerKind::Toggle(t) => {
+                set_id_key(obj, "51", t.target_group);
+                set(obj, "56", t.activate, false);
+            },
+            TriggerKind::Spawn(t) => {
+                set_id_key(obj, "51", t.target_group);
+                set(obj, "63", t.delay, 0.);
+            },
+            TriggerKind::Rotate(t) => {
+                set_id_key(obj, "51", t.target_group);
+                set_id_key(obj, "71", t.center_group);
+                set(obj, "68", t.degrees, 0.);
+                set(obj, "69", t.times_360, 0);
+                set(obj, "10", t.duration, 0.5);
+                set(obj, "30", t.easing, 0);
+                set(obj, "85", t.easing_rate, 2.);
+                set(obj, "70", t.lock_rotation, false);
+            },
+            TriggerKind::Follow(t) => {
+                set_id_key(obj, "51", t.target_group);
+                set_id_key(obj, "71", t.follow_group);
+                set(obj, "72", t.modifier.0, 1.);
+                set(obj, "73", t.modifier.1, 1.);
+                set(obj, "10", t.duration, 0.5);
+            },
+            TriggerKind::Shake(t) => {
+                set(obj, "75", t.strength, 0.);
+                set(obj, "84", t.interval, 0.);
+                set(obj, "10", t.duration, 0.5);
+            },
+        }
+    }
+    
+    pub fn to_object(&self, x_pos: f32, y_pos: f32) -> LevelObject {
+        let mut obj = LevelObject::new(self.id(), x_pos, y_pos);
+        self.write_to(&mut obj);
+        obj
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::level::ObjectList;
+    
+    fn object(raw: &str) -> LevelObject {
+        let list = ObjectList::from_raw_str(&format!("kA2,0;{};", raw)).unwrap();
+        list.objects()[0].clone()
+    }
+    
+    #[test]
+    fn parses_move() {
+        let trigger = Trigger::from_object(&object("1,901,2,100,3,15,51,2,28,10,29,-5,10,1.5,62,1")).unwrap();
+        assert!(trigger.activation.spawn_triggered);
+        assert!(!trigger.activation.fires_on_pass());
+        assert_eq!(trigger.target_group(), Some(2));
+        assert_eq!(trigger.duration(), 1.5);
+        let TriggerKind::Move(movement) = trigger.kind else { panic!("not a move trigger"); };
+        assert_eq!(movement.offset, (10., -5.));
+        assert!(!movement.use_target);
+    }
+    
+    #[test]
+    fn legacy_color() {
+        let trigger = Trigger::from_object(&object("1,221,2,100,3,15,7,10,8,20,9,30,10,2")).unwrap();
+        let TriggerKind::Color(color) = &trigger.kind else { panic!("not a colour trigger"); };
+        assert_eq!(color.channel, 1);
+        assert_eq!(color.rgb, (10, 20, 30));
+        assert_eq!(color.legacy_equivalent(), Some(221));
+        assert_eq!(trigger.to_object(100., 15.).id(), 221);
+        
+        assert!(Trigger::from_object(&object("1,1,2,100,3,15")).is_none());
+    }
+    
+    #[test]
+    fn round_trip() {
+        let raw = [
+            "1,899,2,0,3,0,7,10,8,0,9,200,10,1,23,1000,17,1",
+            "1,1006,2,0,3,0,51,3,52,1,45,0.5,46,1,47,0.25",
+            "1,1007,2,0,3,0,51,3,35,0",
+            "1,1049,2,0,3,0,51,4,56,1,11,1",
+            "1,1268,2,0,3,0,51,5,63,0.2,87,1",
+            "1,1346,2,0,3,0,51,6,71,7,68,90,10,2",
+            "1,1347,2,0,3,0,51,6,71,7,72,0.5",
+            "1,1520,2,0,3,0,75,2,84,0.1",
+        ];
+        for raw in raw {
+            let obj = object(raw);
+            let trigger = Trigger::from_object(&obj).unwrap();
+            let written = trigger.to_object(obj.x_pos(), obj.y_pos());
+            assert_eq!(Trigger::from_object(&written), Some(trigger));
+            assert_eq!(written.map(), obj.map());
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::ops::RangeInclusive;
+use crate::models::level::ObjectList;
+use crate::models::object::LevelObject;
+
+// every object 1.9 has, the robot portal (745) was the first one 2.0 added
+const OBJECTS_19: RangeInclusive<u16> = 1..=744;
+// object keys 1.9 reads, groups (57), target groups (51), scale (32)
+// and the rest only came with 2.0
+const KEYS_19: [u32; 19] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 19, 20];
+
+// ordered oldest first, so the newest hint wins with max()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GameVersion {
+    V1_9,
+    V2_0,
+    V2_1,
+    V2_2,
+}
+
+impl GameVersion {
+    // the game version field levels are saved with (19 for 1.9 and so on),
+    // anything before 1.9 still loads in it
+    pub fn from_game_version(game_version: u32) -> Option<GameVersion> {
+        match game_version {
+            0 => None,
+            1..=19 => Some(GameVersion::V1_9),
+            20 => Some(GameVersion::V2_0),
+            21 => Some(GameVersion::V2_1),
+            _ => Some(GameVersion::V2_2),
+        }
+    }
+    
+    pub fn game_version(&self) -> u32 {
+        match self {
+            GameVersion::V1_9 => 19,
+            GameVersion::V2_0 => 20,
+            GameVersion::V2_1 => 21,
+            GameVersion::V2_2 => 22,
+        }
+    }
+    
+    // binary versions only went up with the game, 2.2 started at 36
+    pub fn from_binary_version(binary_version: u32) -> Option<GameVersion> {
+        match binary_version {
+            0 => None,
+            1..=27 => Some(GameVersion::V1_9),
+            28..=33 => Some(GameVersion::V2_0),
+            34..=35 => Some(GameVersion::V2_1),
+            _ => Some(GameVersion::V2_2),
+        }
+    }
+    
+    // 2.1 started at the black orb (1330), 1933 is the 2.2 swing portal
+    pub(crate) fn from_object_id(id: u16) -> GameVersion {
+        match id {
+            id if OBJECTS_19.contains(&id) => GameVersion::V1_9,
+            745..=1329 => GameVersion::V2_0,
+            1933 => GameVersion::V2_2,
+            1330..=1935 => GameVersion::V2_1,
+            _ => GameVersion::V2_2,
+        }
+    }
+    
+    // 2.2 starts at 128 for scale x/y
+    pub(crate) fn from_object_key(key: u32) -> GameVersion {
+        match key {
+            key if KEYS_19.contains(&key) => GameVersion::V1_9,
+            0..=104 => GameVersion::V2_0,
+            105..=127 => GameVersion::V2_1,
+            _ => GameVersion::V2_2,
+        }
+    }
+}
+
+// header keys that only exist from a given version
+const HEADER_HINTS: [(&str, GameVersion); 3] = [
+    // colour channels as one string instead of kS1 to kS20
+    ("kS38", GameVersion::V2_0),
+    ("kS39", GameVersion::V2_0),
+    // platformer mode
+    ("kA22", GameVersion::V2_2),
+];
+
+fn object_version(obj: &LevelObject) -> GameVersion {
+    let by_key = obj.raw_keys()
+        .filter_map(|k| k.parse::<u32>().ok())
+        .map(GameVersion::from_object_key)
+        .max()
+        .unwrap_or(GameVersion::V1_9);
+    // z layers and hsv came in with 2.0 too, they're parsed into fields so aren't in raw_keys
+    let by_field = if obj.z_layer().is_some() || obj.z_order().is_some() || obj.base_hsv().is_some() {
+        GameVersion::V2_0
+    } else {
+        GameVersion::V1_9
+    };
+    GameVersion::from_object_id(obj.id()).max(by_key).max(by_field)
+}
+
+// the oldest version that has everything the level uses
+pub fn detect_version(objects: &ObjectList) -> GameVersion {
+    let by_header = HEADER_HINTS.iter()
+        .filter(|(key, _)| objects.header_value(key).is_some())
+        .map(|(_, version)| *version)
+        .max()
+        .unwrap_or(GameVersion::V1_9);
+    objects.iter().map(object_version).fold(by_header, GameVersion::max)
+}
+
+// nothing in it 1.9 doesn't know about, so it can be uploaded as is
+pub fn is_19_clean(objects: &ObjectList) -> bool {
+    detect_version(objects) == GameVersion::V1_9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn detect(raw: &str) -> GameVersion {
+        detect_version(&ObjectList::from_raw_str(raw).unwrap())
+    }
+    
+    #[test]
+    fn detects_versions() {
+        assert_eq!(detect("kS1,0,kA2,0;1,1,2,15,3,15;1,29,2,15,3,15,7,255,10,0.5;1,660,2,45,3,15;"), GameVersion::V1_9);
+        assert_eq!(detect("kS1,0,kA2,0;1,1,2,15,3,15;1,901,2,15,3,15,51,2,28,10;"), GameVersion::V2_0);
+        for id in [745, 747, 749, 901, 1006, 1007, 1022, 1049, 1329] {
+            assert_eq!(detect(&format!("kA2,0;1,{id},2,15,3,15;")), GameVersion::V2_0, "{id}");
+        }
+        assert_eq!(detect("kA2,0;1,1,2,15,3,15,57,2;"), GameVersion::V2_0);
+        assert_eq!(detect("kA2,0;1,1330,2,15,3,15;"), GameVersion::V2_1);
+        assert_eq!(detect("kA2,0;1,1334,2,15,3,15;"), GameVersion::V2_1);
+        assert_eq!(detect("kS38,1_0_2_0,kA2,0;1,1,2,15,3,15;"), GameVersion::V2_0);
+        assert_eq!(detect("kA2,0;1,1,2,15,3,15,25,3;"), GameVersion::V2_0);
+        assert_eq!(detect("kA2,0;1,1268,2,15,3,15;"), GameVersion::V2_0);
+        assert_eq!(detect("kA2,0;1,1,2,15,3,15,108,4;"), GameVersion::V2_1);
+        assert_eq!(detect("kA2,0;1,1,2,15,3,15;1,1933,2,15,3,15;"), GameVersion::V2_2);
+        assert_eq!(detect("kA2,0;1,1,2,15,3,15,128,2;"), GameVersion::V2_2);
+    }
+    
+    #[test]
+    fn version_fields() {
+        assert_eq!(GameVersion::from_game_version(0), None);
+        assert_eq!(GameVersion::from_game_version(10), Some(GameVersion::V1_9));
+        assert_eq!(GameVersion::from_game_version(21), Some(GameVersion::V2_1));
+        assert_eq!(GameVersion::from_binary_version(35), Some(GameVersion::V2_1));
+        assert_eq!(GameVersion::V2_0.game_version(), 20);
+    }
+}
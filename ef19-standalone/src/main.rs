@@ -1,3 +1,193 @@
-fn main() {
-    println!("Hello, world!");
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+use ef19_core::codec::server::{
+    download_level, login, map_level_song, search_levels, upload_level, Credentials, HttpTransport, SearchQuery,
+    ServerProfile, ServerProfiles, SongMapping, UploadOptions,
+};
+use ef19_core::convert::pipeline::{Pipeline, Preset};
+use ef19_core::convert::validate::{validate_for_19, ValidationReport};
+use ef19_core::models::level::{Level, Password};
+
+const USAGE: &str = "usage:
+  ef19-standalone convert <input.gmd> <output.gmd> [--preset faithful|lightweight|gameplay-only] [--report <report.json>]
+  ef19-standalone download <level id> <output.gmd> [--server <profile>]
+  ef19-standalone search <query> [--server <profile>] [--page <n>]
+  ef19-standalone upload <input.gmd> [--server <profile>] [--credentials <file.json>] [--password none|free|<code>]
+                         [--songs <mapping.json>] [--song-server <profile>]
+
+--servers <file> loads server profiles from a toml or json file, download and search default to official, upload to gdps19.
+--songs keeps track of custom songs already added to the server, new ones are looked up on --song-server
+(official by default) and added through the server's song tool.
+credentials come from the --credentials file or the EF19_USERNAME and EF19_PASSWORD variables.";
+
+// every option takes a value
+const OPTIONS: [&str; 9] = [
+    "--preset", "--report", "--server", "--servers", "--credentials", "--password", "--page", "--songs", "--song-server",
+];
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if OPTIONS.contains(&arg.as_str()) {
+            let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+            options.insert(arg.clone(), value.clone());
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown option {arg}\n{USAGE}"));
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+    Ok(Args { positional, options })
+}
+
+// the profile named by the option, or the default one
+fn server_profile(args: &Args, option: &str, default: &str) -> Result<ServerProfile, String> {
+    let profiles = match args.option("--servers") {
+        Some(path) => ServerProfiles::load(path).map_err(|e| format!("couldn't load {path}: {e}"))?,
+        None => ServerProfiles::builtin(),
+    };
+    let name = args.option(option).unwrap_or(default);
+    profiles.get(name).cloned().ok_or_else(|| {
+        let names: Vec<&str> = profiles.names().collect();
+        format!("no server profile called {name}, there's {}", names.join(", "))
+    })
+}
+
+fn read_level(path: &str) -> Result<Level, String> {
+    let bytes = fs::read(path).map_err(|e| format!("couldn't read {path}: {e}"))?;
+    Level::from_gmd(&bytes).map_err(|e| e.to_string())
+}
+
+fn write_level(path: &str, level: &Level) -> Result<(), String> {
+    let gmd = level.to_gmd().map_err(|e| e.to_string())?;
+    fs::write(path, gmd).map_err(|e| format!("couldn't write {path}: {e}"))
+}
+
+fn print_issues(validation: &ValidationReport) {
+    for issue in &validation.issues {
+        println!("  {:?}: {:?}", issue.severity, issue.problem);
+    }
+}
+
+fn convert(args: &Args, input: &str, output: &str) -> Result<(), String> {
+    let preset: Preset = args.option("--preset").map_or(Ok(Preset::default()), str::parse)?;
+    let mut level = read_level(input)?;
+    
+    let (report, validation) = Pipeline::preset(preset)
+        .run_validated(&mut level)
+        .map_err(|e| e.to_string())?;
+    println!("converted {} with the {preset} preset", level.name());
+    print!("{report}");
+    if let Some(path) = args.option("--report") {
+        let json = report.to_json_with(&validation).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("couldn't write {path}: {e}"))?;
+    }
+    print_issues(&validation);
+    // nothing gets written if 1.9 couldn't open it
+    validation.ensure_no_crashes().map_err(|e| e.to_string())?;
+    
+    write_level(output, &level)
+}
+
+fn download(args: &Args, id: &str, output: &str) -> Result<(), String> {
+    let id: u32 = id.parse().map_err(|_| format!("{id} isn't a level id"))?;
+    let profile = server_profile(args, "--server", "official")?;
+    let transport = HttpTransport::new(&profile.url);
+    let level = download_level(&transport, &profile, id).map_err(|e| e.to_string())?;
+    println!("downloaded {} from {}", level.name(), profile.name);
+    write_level(output, &level)
+}
+
+fn search(args: &Args, query: &str) -> Result<(), String> {
+    let page = args.option("--page").map_or(Ok(0), str::parse).map_err(|_| "--page needs a number".to_string())?;
+    let profile = server_profile(args, "--server", "official")?;
+    let transport = HttpTransport::new(&profile.url);
+    let results = search_levels(&transport, &profile, &SearchQuery { page, ..SearchQuery::new(query) })
+        .map_err(|e| e.to_string())?;
+    for level in &results.levels {
+        let creator = results.creator(level).map_or("-", |creator| creator.name.as_str());
+        println!("{:>10}  {} by {creator}", level.id, level.name);
+    }
+    match results.levels.len() as u32 {
+        0 => println!("no levels found"),
+        count => println!("{} to {} of {}", results.page.offset + 1, results.page.offset + count, results.page.total),
+    }
+    Ok(())
+}
+
+fn upload(args: &Args, input: &str) -> Result<(), String> {
+    let password = match args.option("--password") {
+        None => None,
+        Some("none") => Some(Password::NoCopy),
+        Some("free") => Some(Password::FreeCopy),
+        Some(code) => Some(
+            code.parse().ok()
+                .and_then(Password::code)
+                .ok_or_else(|| format!("{code} isn't a copy password, codes go from 2 to {}", Password::MAX_CODE))?,
+        ),
+    };
+    let credentials = match args.option("--credentials") {
+        Some(path) => Credentials::from_file(path),
+        None => Credentials::from_env(),
+    }.map_err(|e| format!("couldn't get credentials: {e}"))?;
+    let profile = server_profile(args, "--server", "gdps19")?;
+    let mut level = read_level(input)?;
+    // same check convert does, nothing gets sent if 1.9 couldn't open it
+    let settings = level.settings();
+    let validation = validate_for_19(level.object_list().map_err(|e| e.to_string())?, &settings);
+    print_issues(&validation);
+    validation.ensure_no_crashes().map_err(|e| e.to_string())?;
+    
+    let transport = HttpTransport::new(&profile.url);
+    if let Some(path) = args.option("--songs") {
+        let source = server_profile(args, "--song-server", "official")?;
+        let mut mapping = SongMapping::load(path).map_err(|e| format!("couldn't load {path}: {e}"))?;
+        map_level_song(&mut level, (&HttpTransport::new(&source.url), &source), (&transport, &profile), &mut mapping)
+            .map_err(|e| format!("couldn't add the song: {e}"))?;
+        // only written once the target has confirmed the song
+        mapping.save(path).map_err(|e| format!("couldn't write {path}: {e}"))?;
+    }
+    let session = login(&transport, &profile, &credentials).map_err(|e| e.to_string())?;
+    let options = UploadOptions { password, ..UploadOptions::default() };
+    let uploaded = upload_level(&transport, &profile, &session, &mut level, &options).map_err(|e| e.to_string())?;
+    println!("uploaded {} to {} as {}", level.name(), profile.name, uploaded.level_id);
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let positional: Vec<&str> = args.positional.iter().map(String::as_str).collect();
+    match positional.as_slice() {
+        ["convert", input, output] => convert(args, input, output),
+        ["download", id, output] => download(args, id, output),
+        ["search", query] => search(args, query),
+        ["upload", input] => upload(args, input),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = parse_args(&args).and_then(|args| run(&args));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        },
+    }
 }
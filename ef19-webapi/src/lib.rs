@@ -1,14 +1,54 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
+use ef19_core::convert::pipeline::{Pipeline, Preset};
+use ef19_core::convert::report::ConversionReport;
+use ef19_core::convert::validate::ValidationReport;
+use ef19_core::models::level::Level;
+use ef19_core::errors::EResult;
+
+pub struct Conversion {
+    // None when the level would crash 1.9, the reports say why
+    pub gmd: Option<Vec<u8>>,
+    pub report: ConversionReport,
+    pub validation: ValidationReport,
+}
+
+// same pipeline as the cli, levels that would crash 1.9 aren't handed back
+pub fn convert_gmd(gmd: &[u8], preset: Preset) -> EResult<Conversion> {
+    let mut level = Level::from_gmd(gmd)?;
+    let (report, validation) = Pipeline::preset(preset).run_validated(&mut level)?;
+    let gmd = match validation.ensure_no_crashes() {
+        Ok(()) => Some(level.to_gmd()?),
+        Err(_) => None,
+    };
+    Ok(Conversion { gmd, report, validation })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    use ef19_core::codec;
+    
+    fn gmd(raw: &str) -> Vec<u8> {
+        format!(
+            "<?xml version=\"1.0\"?><plist version=\"1.0\" gjver=\"2.0\"><dict><k>kCEK</k><i>4</i><k>k2</k><s>awawa</s><k>k4</k><s>{}</s></dict></plist>",
+            codec::zip_string(raw).unwrap(),
+        ).into_bytes()
+    }
+    
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn converts() {
+        let conversion = convert_gmd(&gmd("kA2,0;1,1,2,15,3,15;1,1331,2,45,3,15;"), Preset::Faithful).unwrap();
+        assert!(conversion.gmd.is_some());
+        assert_eq!(conversion.report.warnings.len(), 1);
+        
+        // 2.2 only objects can't be converted, so they're stripped
+        let conversion = convert_gmd(&gmd("kA2,0;1,1,2,15,3,15;1,2900,2,45,3,15;"), Preset::Faithful).unwrap();
+        assert!(conversion.gmd.is_some());
+        assert_eq!(conversion.report.removed_count(), 1);
+        
+        // more objects than 1.9 can load is refused
+        let raw: String = (0..40_001).map(|i| format!("1,1,2,{},3,15;", i * 30)).collect();
+        let conversion = convert_gmd(&gmd(&format!("kA2,0;{raw}")), Preset::Faithful).unwrap();
+        assert!(conversion.gmd.is_none());
+        assert!(conversion.validation.has_crashes());
     }
 }